@@ -0,0 +1,74 @@
+use std::time::{Duration, Instant};
+
+use fruits_ecs_component::{Component, Entity, WorldEntitiesComponents};
+
+struct Marker<const N: usize>(#[allow(dead_code)] u32);
+impl<const N: usize> Component for Marker<N> { }
+
+const MARKERS_COUNT: usize = 8;
+const ARCHETYPES_COUNT: usize = 1 << MARKERS_COUNT;
+const ENTITIES_PER_ARCHETYPE_COUNT: usize = 40;
+const QUERIES_COUNT: usize = 10_000;
+const RUNS_COUNT: usize = 5;
+
+fn add_marker(entities_components: &mut WorldEntitiesComponents, entity: Entity, marker: usize) {
+    match marker {
+        0 => { entities_components.add_component(entity, Marker::<0>(0)); },
+        1 => { entities_components.add_component(entity, Marker::<1>(1)); },
+        2 => { entities_components.add_component(entity, Marker::<2>(2)); },
+        3 => { entities_components.add_component(entity, Marker::<3>(3)); },
+        4 => { entities_components.add_component(entity, Marker::<4>(4)); },
+        5 => { entities_components.add_component(entity, Marker::<5>(5)); },
+        6 => { entities_components.add_component(entity, Marker::<6>(6)); },
+        7 => { entities_components.add_component(entity, Marker::<7>(7)); },
+        _ => unreachable!(),
+    };
+}
+
+/// Spreads the entities over every combination of markers.
+fn spawn(entities_components: &mut WorldEntitiesComponents) {
+    for _ in 0..ENTITIES_PER_ARCHETYPE_COUNT {
+        for archetype in 0..ARCHETYPES_COUNT {
+            let entity = entities_components.create_entity();
+
+            for marker in (0..MARKERS_COUNT).filter(|m| archetype & (1 << m) != 0) {
+                add_marker(entities_components, entity, marker);
+            }
+        }
+    }
+}
+
+fn measure(name: &str, operations_count: usize, operation: &str, mut f: impl FnMut() -> Duration) {
+    let best = (0..RUNS_COUNT).map(|_| f()).min().unwrap();
+
+    println!(
+        "{name:<10} {:>10.3} ms total, {:>8.1} ns per {operation}",
+        best.as_secs_f64() * 1000.0,
+        best.as_secs_f64() * 1e9 / operations_count as f64,
+    );
+}
+
+fn main() {
+    measure("spawn", ARCHETYPES_COUNT * ENTITIES_PER_ARCHETYPE_COUNT, "entity", || {
+        let mut entities_components = WorldEntitiesComponents::new();
+
+        let timer = Instant::now();
+        spawn(&mut entities_components);
+        timer.elapsed()
+    });
+
+    measure("query", QUERIES_COUNT, "query", || {
+        let mut entities_components = WorldEntitiesComponents::new();
+        spawn(&mut entities_components);
+
+        let timer = Instant::now();
+
+        let matched_count = (0..QUERIES_COUNT)
+            .map(|_| entities_components.query::<(&Marker<1>, &Marker<4>, &Marker<6>)>().len())
+            .sum::<usize>();
+
+        assert_eq!(matched_count, QUERIES_COUNT * ARCHETYPES_COUNT / 8 * ENTITIES_PER_ARCHETYPE_COUNT);
+
+        timer.elapsed()
+    });
+}
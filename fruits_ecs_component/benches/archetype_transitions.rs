@@ -0,0 +1,72 @@
+use std::time::{Duration, Instant};
+
+use fruits_ecs_component::{Component, Entity, WorldEntitiesComponents};
+
+struct Position(#[allow(dead_code)] [f32; 3]);
+impl Component for Position { }
+
+struct Velocity(#[allow(dead_code)] [f32; 3]);
+impl Component for Velocity { }
+
+struct Health(#[allow(dead_code)] u32);
+impl Component for Health { }
+
+struct Toggled;
+impl Component for Toggled { }
+
+const ENTITIES_COUNT: usize = 10_000;
+const TOGGLES_COUNT: usize = 10;
+const RUNS_COUNT: usize = 5;
+
+fn spawn(entities_components: &mut WorldEntitiesComponents) -> Vec<Entity> {
+    (0..ENTITIES_COUNT).map(|_| {
+        let entity = entities_components.create_entity();
+
+        entities_components.add_component(entity, Position([0.0; 3]));
+        entities_components.add_component(entity, Velocity([0.0; 3]));
+        entities_components.add_component(entity, Health(100));
+
+        entity
+    }).collect()
+}
+
+fn toggle(entities_components: &mut WorldEntitiesComponents, entities: &[Entity]) {
+    for _ in 0..TOGGLES_COUNT {
+        for &entity in entities {
+            entities_components.add_component(entity, Toggled);
+        }
+
+        for &entity in entities {
+            entities_components.remove_component::<Toggled>(entity);
+        }
+    }
+}
+
+fn measure(name: &str, operations_count: usize, mut f: impl FnMut() -> Duration) {
+    let best = (0..RUNS_COUNT).map(|_| f()).min().unwrap();
+
+    println!(
+        "{name:<10} {:>10.3} ms total, {:>8.1} ns per structural change",
+        best.as_secs_f64() * 1000.0,
+        best.as_secs_f64() * 1e9 / operations_count as f64,
+    );
+}
+
+fn main() {
+    measure("spawn", ENTITIES_COUNT * 3, || {
+        let mut entities_components = WorldEntitiesComponents::new();
+
+        let timer = Instant::now();
+        spawn(&mut entities_components);
+        timer.elapsed()
+    });
+
+    measure("toggle", ENTITIES_COUNT * TOGGLES_COUNT * 2, || {
+        let mut entities_components = WorldEntitiesComponents::new();
+        let entities = spawn(&mut entities_components);
+
+        let timer = Instant::now();
+        toggle(&mut entities_components, &entities);
+        timer.elapsed()
+    });
+}
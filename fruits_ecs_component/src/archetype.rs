@@ -1,73 +1,328 @@
-use std::{any::TypeId, marker::PhantomData, sync::Arc};
+use std::{any::TypeId, collections::HashSet, marker::PhantomData, ops::Range, sync::Arc};
 
 use fruits_ecs_data_usage::{DataUsageEntry, PerTypeDataUsage};
 
-use super::{archetype_layout::ArchetypeLayout, component::Component, entity::Entity, unique_components_set::UniqueComponentsSet, unsafe_archetype::{ArchetypeItemPhysicalLocation, UnsafeArchetype}};
+use super::{
+    archetype_layout::ArchetypeLayout, change_detection::{ComponentTicks, RunTicks, Tick}, component::{Component, StorageType}, disabled::is_disabled_marker, entity::Entity, sparse_set::{ComponentSparseSet, WorldSparseSets},
+    type_info::ComponentTypeId, unique_components_set::UniqueComponentsSet, unsafe_archetype::UnsafeArchetype,
+};
+
+/// Everything an item needs to resolve its columns in a chunk of the archetype.
+#[derive(Clone, Copy)]
+pub struct FetchContext<'w> {
+    pub archetype: &'w Archetype,
+    pub sparse_sets: &'w WorldSparseSets,
+    pub ticks: RunTicks,
+}
 
 pub unsafe trait ArchetypeIteratorItem {
     type Item<'w>: 'w + ArchetypeIteratorItem;
     type ReadOnlyItem<'w>: 'w + ArchetypeIteratorItem;
-    
-    fn from_archetype<'w>(entity_index: usize, archetype: &'w UnsafeArchetype, layout: &ArchetypeLayout) -> Self::Item<'w>;
+    /// Pointers to the item columns of a single archetype chunk.
+    type Column<'w>: Copy;
+    /// Items of all the alive entities of a single archetype chunk.
+    type Slice<'w>;
+
+    fn column<'w>(context: FetchContext<'w>, chunk_index: usize) -> Self::Column<'w>;
+    /// Whether the row contains the item. Always true for dense items.
+    /// # Safety
+    /// `entity_in_chunk_index` should be less than the count of alive entities in the column chunk.
+    unsafe fn matches_row<'w>(column: Self::Column<'w>, entity_in_chunk_index: usize) -> bool;
+    /// # Safety
+    /// `entity_in_chunk_index` should be less than the count of alive entities in the column chunk
+    /// and the row should match the item.
+    unsafe fn from_column<'w>(column: Self::Column<'w>, entity_in_chunk_index: usize) -> Self::Item<'w>;
+    /// # Safety
+    /// `entities_count` should be the count of alive entities in the column chunk.
+    unsafe fn slice_from_column<'w>(column: Self::Column<'w>, entities_count: usize) -> Self::Slice<'w>;
     fn fill_usage(usage: &mut PerTypeDataUsage);
+    /// Components every matching archetype should contain.
+    fn fill_archetype_components(components: &mut HashSet<TypeId>);
+    /// Whether the item can be fetched from the archetype rows at all.
+    fn matches_archetype(archetype: &Archetype) -> bool;
+    /// Whether every row of a matching archetype matches the item, so chunks can be sliced.
+    const IS_DENSE: bool;
+    /// Whether the query yields the entities with the `Disabled` marker.
+    fn includes_disabled() -> bool {
+        false
+    }
+
+    fn from_archetype<'w>(context: FetchContext<'w>, entity_index: usize) -> Option<Self::Item<'w>> {
+        let layout = context.archetype.layout();
+
+        let column = Self::column(context, layout.chunk_index(entity_index));
+        let entity_in_chunk_index = layout.entity_in_chunk_index(entity_index);
+
+        unsafe {
+            if !Self::matches_row(column, entity_in_chunk_index) {
+                return None;
+            }
+
+            Some(Self::from_column(column, entity_in_chunk_index))
+        }
+    }
+}
+
+pub enum ComponentColumn<'w, C: Component> {
+    Table {
+        components: *mut C,
+        ticks: *mut ComponentTicks,
+    },
+    SparseSet {
+        entities: *const Entity,
+        sparse_set: Option<&'w ComponentSparseSet<C>>,
+    },
+}
+
+impl<'w, C: Component> Clone for ComponentColumn<'w, C> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'w, C: Component> Copy for ComponentColumn<'w, C> { }
+
+impl<'w, C: Component> ComponentColumn<'w, C> {
+    pub(crate) fn new(context: FetchContext<'w>, chunk_index: usize) -> Self {
+        let layout = context.archetype.layout();
+        let archetype = unsafe { context.archetype.unsafe_archetype() };
+
+        match C::STORAGE_TYPE {
+            StorageType::Table => {
+                let column_location = layout.component_column_physical_location(chunk_index, &ComponentTypeId::of::<C>());
+                let ticks_column_location = layout.component_ticks_column_physical_location(chunk_index, &ComponentTypeId::of::<C>());
+
+                ComponentColumn::Table {
+                    components: unsafe { archetype.get_memory(&column_location).0 as *mut C },
+                    ticks: unsafe { archetype.get_memory(&ticks_column_location).0 as *mut ComponentTicks },
+                }
+            },
+            StorageType::SparseSet => {
+                let entities_location = layout.entity_column_physical_location(chunk_index);
+
+                ComponentColumn::SparseSet {
+                    entities: unsafe { archetype.get_memory(&entities_location).0 as *const Entity },
+                    sparse_set: context.sparse_sets.get::<C>(),
+                }
+            },
+        }
+    }
+
+    pub(crate) unsafe fn contains(self, entity_in_chunk_index: usize) -> bool {
+        match self {
+            ComponentColumn::Table { .. } => true,
+            ComponentColumn::SparseSet { entities, sparse_set } => {
+                sparse_set.is_some_and(|s| s.contains(*entities.add(entity_in_chunk_index)))
+            },
+        }
+    }
+
+    unsafe fn get(self, entity_in_chunk_index: usize) -> *mut C {
+        match self {
+            ComponentColumn::Table { components, .. } => components.add(entity_in_chunk_index),
+            ComponentColumn::SparseSet { entities, sparse_set } => {
+                sparse_set.unwrap().get_ptr(*entities.add(entity_in_chunk_index)).unwrap()
+            },
+        }
+    }
+
+    pub(crate) unsafe fn get_ticks(self, entity_in_chunk_index: usize) -> *mut ComponentTicks {
+        match self {
+            ComponentColumn::Table { ticks, .. } => ticks.add(entity_in_chunk_index),
+            ComponentColumn::SparseSet { entities, sparse_set } => {
+                sparse_set.unwrap().get_ticks_ptr(*entities.add(entity_in_chunk_index)).unwrap()
+            },
+        }
+    }
+
+    fn table(self) -> (*mut C, *mut ComponentTicks) {
+        let ComponentColumn::Table { components, ticks } = self else {
+            panic!("Sparse set components cannot be sliced by chunks. Component: {}.", std::any::type_name::<C>());
+        };
+
+        (components, ticks)
+    }
+
+    pub(crate) fn fill_archetype_components(components: &mut HashSet<TypeId>) {
+        if C::STORAGE_TYPE == StorageType::Table {
+            components.insert(TypeId::of::<C>());
+        }
+    }
+
+    /// Sparse set components can be in any archetype, table components only in the archetypes containing them.
+    pub(crate) fn matches_archetype(archetype: &Archetype) -> bool {
+        C::STORAGE_TYPE == StorageType::SparseSet || archetype.contains_component_type::<C>()
+    }
 }
 
 unsafe impl<'a, C: Component> ArchetypeIteratorItem for &'a C {
     type Item<'w> = &'w C;
     type ReadOnlyItem<'w> = &'w C;
+    type Column<'w> = ComponentColumn<'w, C>;
+    type Slice<'w> = &'w [C];
     
-    fn from_archetype<'w>(entity_index: usize, archetype: &'w UnsafeArchetype, layout: &ArchetypeLayout) -> Self::Item<'w> {
-        let item_location = layout.component_memory_physical_location(entity_index, &TypeId::of::<C>());
+    fn column<'w>(context: FetchContext<'w>, chunk_index: usize) -> Self::Column<'w> {
+        ComponentColumn::new(context, chunk_index)
+    }
 
-        unsafe {
-            let memory_ref = archetype.get_memory(&item_location);
+    unsafe fn matches_row<'w>(column: Self::Column<'w>, entity_in_chunk_index: usize) -> bool {
+        column.contains(entity_in_chunk_index)
+    }
 
-            &*(memory_ref.0 as *const C)
-        }
+    unsafe fn from_column<'w>(column: Self::Column<'w>, entity_in_chunk_index: usize) -> Self::Item<'w> {
+        &*column.get(entity_in_chunk_index)
+    }
+
+    unsafe fn slice_from_column<'w>(column: Self::Column<'w>, entities_count: usize) -> Self::Slice<'w> {
+        std::slice::from_raw_parts(column.table().0, entities_count)
     }
     
     fn fill_usage(usage: &mut PerTypeDataUsage) {
         usage.add(DataUsageEntry::new_readonly(TypeId::of::<C>()));
     }
+
+    fn fill_archetype_components(components: &mut HashSet<TypeId>) {
+        ComponentColumn::<C>::fill_archetype_components(components);
+    }
+
+    fn matches_archetype(archetype: &Archetype) -> bool {
+        ComponentColumn::<C>::matches_archetype(archetype)
+    }
+
+    const IS_DENSE: bool = matches!(C::STORAGE_TYPE, StorageType::Table);
+
+    fn includes_disabled() -> bool {
+        is_disabled_marker::<C>()
+    }
 }
 
+/// Mutable access marks the component as changed at the current run tick.
 unsafe impl<C: Component> ArchetypeIteratorItem for &mut C {
     type Item<'w> = &'w mut C;
     type ReadOnlyItem<'w> = &'w C;
+    type Column<'w> = (ComponentColumn<'w, C>, Tick);
+    type Slice<'w> = &'w mut [C];
     
-    fn from_archetype<'w>(entity_index: usize, archetype: &'w UnsafeArchetype, layout: &ArchetypeLayout) -> Self::Item<'w> {
-        let item_location = layout.component_memory_physical_location(entity_index, &TypeId::of::<C>());
+    fn column<'w>(context: FetchContext<'w>, chunk_index: usize) -> Self::Column<'w> {
+        (ComponentColumn::new(context, chunk_index), context.ticks.this_run)
+    }
 
-        unsafe {
-            let memory_ref = archetype.get_memory(&item_location);
+    unsafe fn matches_row<'w>((column, _): Self::Column<'w>, entity_in_chunk_index: usize) -> bool {
+        column.contains(entity_in_chunk_index)
+    }
+
+    unsafe fn from_column<'w>((column, this_run): Self::Column<'w>, entity_in_chunk_index: usize) -> Self::Item<'w> {
+        (*column.get_ticks(entity_in_chunk_index)).changed = this_run;
+
+        &mut *column.get(entity_in_chunk_index)
+    }
+
+    unsafe fn slice_from_column<'w>((column, this_run): Self::Column<'w>, entities_count: usize) -> Self::Slice<'w> {
+        let (components, ticks) = column.table();
 
-            &mut *(memory_ref.0 as *mut C)
+        for ticks in std::slice::from_raw_parts_mut(ticks, entities_count) {
+            ticks.changed = this_run;
         }
+
+        std::slice::from_raw_parts_mut(components, entities_count)
     }
     
     fn fill_usage(usage: &mut PerTypeDataUsage) {
         usage.add(DataUsageEntry::new_mutable(TypeId::of::<C>()));
     }
+
+    fn fill_archetype_components(components: &mut HashSet<TypeId>) {
+        ComponentColumn::<C>::fill_archetype_components(components);
+    }
+
+    fn matches_archetype(archetype: &Archetype) -> bool {
+        ComponentColumn::<C>::matches_archetype(archetype)
+    }
+
+    const IS_DENSE: bool = matches!(C::STORAGE_TYPE, StorageType::Table);
+
+    fn includes_disabled() -> bool {
+        is_disabled_marker::<C>()
+    }
 }
 
 unsafe impl ArchetypeIteratorItem for Entity {
     type Item<'w> = Entity;
     type ReadOnlyItem<'w> = Entity;
+    type Column<'w> = *const Entity;
+    type Slice<'w> = &'w [Entity];
     
-    fn from_archetype<'w>(entity_index: usize, archetype: &'w UnsafeArchetype, layout: &ArchetypeLayout) -> Self::Item<'w> {
-        let item_location = layout.entity_memory_physical_location(entity_index);
+    fn column<'w>(context: FetchContext<'w>, chunk_index: usize) -> Self::Column<'w> {
+        let column_location = context.archetype.layout().entity_column_physical_location(chunk_index);
 
-        unsafe {
-            let memory_ref = archetype.get_memory(&item_location);
+        unsafe { context.archetype.unsafe_archetype().get_memory(&column_location).0 as *const Entity }
+    }
 
-            *(memory_ref.0 as *const Entity)
-        }
+    unsafe fn matches_row<'w>(_column: Self::Column<'w>, _entity_in_chunk_index: usize) -> bool {
+        true
+    }
+
+    unsafe fn from_column<'w>(column: Self::Column<'w>, entity_in_chunk_index: usize) -> Self::Item<'w> {
+        *column.add(entity_in_chunk_index)
+    }
+
+    unsafe fn slice_from_column<'w>(column: Self::Column<'w>, entities_count: usize) -> Self::Slice<'w> {
+        std::slice::from_raw_parts(column, entities_count)
     }
     
     fn fill_usage(usage: &mut PerTypeDataUsage) {
         usage.add(DataUsageEntry::new_readonly(TypeId::of::<Entity>()));
     }
-    
+
+    fn fill_archetype_components(_components: &mut HashSet<TypeId>) { }
+
+    fn matches_archetype(_archetype: &Archetype) -> bool {
+        true
+    }
+
+    const IS_DENSE: bool = true;
+}
+
+/// Fetches `None` from the rows without the item instead of skipping them.
+unsafe impl<A: ArchetypeIteratorItem> ArchetypeIteratorItem for Option<A> {
+    type Item<'w> = Option<A::Item<'w>>;
+    type ReadOnlyItem<'w> = Option<A::ReadOnlyItem<'w>>;
+    type Column<'w> = Option<A::Column<'w>>;
+    type Slice<'w> = Option<A::Slice<'w>>;
+
+    fn column<'w>(context: FetchContext<'w>, chunk_index: usize) -> Self::Column<'w> {
+        A::matches_archetype(context.archetype).then(|| A::column(context, chunk_index))
+    }
+
+    unsafe fn matches_row<'w>(_column: Self::Column<'w>, _entity_in_chunk_index: usize) -> bool {
+        true
+    }
+
+    unsafe fn from_column<'w>(column: Self::Column<'w>, entity_in_chunk_index: usize) -> Self::Item<'w> {
+        let column = column?;
+
+        A::matches_row(column, entity_in_chunk_index).then(|| A::from_column(column, entity_in_chunk_index))
+    }
+
+    unsafe fn slice_from_column<'w>(column: Self::Column<'w>, entities_count: usize) -> Self::Slice<'w> {
+        column.map(|c| A::slice_from_column(c, entities_count))
+    }
+
+    fn fill_usage(usage: &mut PerTypeDataUsage) {
+        A::fill_usage(usage);
+    }
+
+    fn fill_archetype_components(_components: &mut HashSet<TypeId>) { }
+
+    fn matches_archetype(_archetype: &Archetype) -> bool {
+        true
+    }
+
+    const IS_DENSE: bool = A::IS_DENSE;
+
+    fn includes_disabled() -> bool {
+        A::includes_disabled()
+    }
 }
 
 macro_rules! archetype_iterator_item_impl {
@@ -83,16 +338,61 @@ macro_rules! archetype_iterator_item_impl {
             type ReadOnlyItem<'w> = (
                 $($P::ReadOnlyItem<'w>),+
             );
+            type Column<'w> = (
+                $($P::Column<'w>),+
+            );
+            type Slice<'w> = (
+                $($P::Slice<'w>),+
+            );
             
-            fn from_archetype<'w>(entity_index: usize, archetype: &'w UnsafeArchetype, layout: &ArchetypeLayout) -> Self::Item<'w> {
+            fn column<'w>(context: FetchContext<'w>, chunk_index: usize) -> Self::Column<'w> {
                 (
-                    $($P::from_archetype(entity_index, archetype, layout)),+
+                    $($P::column(context, chunk_index)),+
+                )
+            }
+
+            #[allow(non_snake_case)]
+            unsafe fn matches_row<'w>(column: Self::Column<'w>, entity_in_chunk_index: usize) -> bool {
+                let ($($P),+) = column;
+
+                $($P::matches_row($P, entity_in_chunk_index))&&+
+            }
+
+            #[allow(non_snake_case)]
+            unsafe fn from_column<'w>(column: Self::Column<'w>, entity_in_chunk_index: usize) -> Self::Item<'w> {
+                let ($($P),+) = column;
+
+                (
+                    $($P::from_column($P, entity_in_chunk_index)),+
+                )
+            }
+
+            #[allow(non_snake_case)]
+            unsafe fn slice_from_column<'w>(column: Self::Column<'w>, entities_count: usize) -> Self::Slice<'w> {
+                let ($($P),+) = column;
+
+                (
+                    $($P::slice_from_column($P, entities_count)),+
                 )
             }
             
             fn fill_usage(usage: &mut PerTypeDataUsage) {
                 $($P::fill_usage(usage));+;
             }
+
+            fn fill_archetype_components(components: &mut HashSet<TypeId>) {
+                $($P::fill_archetype_components(components));+;
+            }
+
+            fn matches_archetype(archetype: &Archetype) -> bool {
+                $($P::matches_archetype(archetype))&&+
+            }
+
+            const IS_DENSE: bool = $($P::IS_DENSE)&&+;
+
+            fn includes_disabled() -> bool {
+                $($P::includes_disabled())||+
+            }
         }
     };
 }
@@ -114,20 +414,29 @@ archetype_iterator_item_impl!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9, P10, P11,
 
 
 pub struct ArchetypeIterator<'a, A: ArchetypeIteratorItem> {
-    archetype: &'a UnsafeArchetype,
-    archetype_layout: Arc<ArchetypeLayout>,
-    entities_count: usize,
+    context: FetchContext<'a>,
     entity_index: usize,
+    end_entity_index: usize,
+    chunk_column: Option<A::Column<'a>>,
     _phantom: PhantomData<&'a mut A>,
 }
 
 impl<'a, A: ArchetypeIteratorItem> ArchetypeIterator<'a, A> {
-    pub fn new(archetype: &'a UnsafeArchetype, archetype_layout: Arc<ArchetypeLayout>, entities_count: usize) -> Self {
+    pub fn new(context: FetchContext<'a>) -> Self {
+        let entities_count = context.archetype.entities_count();
+
+        Self::new_in_range(context, 0..entities_count)
+    }
+
+    /// Iterates over the entities with archetype indices in `range`.
+    pub fn new_in_range(context: FetchContext<'a>, range: Range<usize>) -> Self {
+        assert!(range.end <= context.archetype.entities_count());
+
         Self {
-            archetype,
-            archetype_layout,
-            entities_count,
-            entity_index: 0,
+            context,
+            entity_index: range.start,
+            end_entity_index: range.end,
+            chunk_column: None,
             _phantom: Default::default(),
         }
     }
@@ -137,13 +446,80 @@ impl<'a, A: ArchetypeIteratorItem> Iterator for ArchetypeIterator<'a, A> {
     type Item = A::Item<'a>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.entity_index >= self.entities_count {
+        let layout = self.context.archetype.layout();
+
+        while self.entity_index < self.end_entity_index {
+            let entity_in_chunk_index = layout.entity_in_chunk_index(self.entity_index);
+
+            let chunk_column = match self.chunk_column {
+                Some(chunk_column) if entity_in_chunk_index != 0 => chunk_column,
+                _ => {
+                    let chunk_index = layout.chunk_index(self.entity_index);
+                    *self.chunk_column.insert(A::column(self.context, chunk_index))
+                },
+            };
+
+            self.entity_index += 1;
+
+            unsafe {
+                if A::matches_row(chunk_column, entity_in_chunk_index) {
+                    return Some(A::from_column(chunk_column, entity_in_chunk_index));
+                }
+            }
+        }
+
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.end_entity_index.saturating_sub(self.entity_index);
+
+        match A::IS_DENSE {
+            true => (remaining, Some(remaining)),
+            false => (0, Some(remaining)),
+        }
+    }
+}
+
+pub struct ArchetypeChunksIterator<'a, A: ArchetypeIteratorItem> {
+    context: FetchContext<'a>,
+    chunk_index: usize,
+    _phantom: PhantomData<&'a mut A>,
+}
+
+impl<'a, A: ArchetypeIteratorItem> ArchetypeChunksIterator<'a, A> {
+    pub fn new(context: FetchContext<'a>) -> Self {
+        // Sparse set rows are not contiguous, so querying them by chunks does not compile.
+        const { assert!(A::IS_DENSE, "Only dense items can be iterated by chunks.") };
+
+        Self {
+            context,
+            chunk_index: 0,
+            _phantom: Default::default(),
+        }
+    }
+}
+
+impl<'a, A: ArchetypeIteratorItem> Iterator for ArchetypeChunksIterator<'a, A> {
+    type Item = A::Slice<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entities_count = self.context.archetype.entities_count();
+        let entities_per_chunk_count = self.context.archetype.layout().entities_per_chunk_count();
+
+        let chunk_start = self.chunk_index * entities_per_chunk_count;
+
+        if chunk_start >= entities_count {
             return None;
         }
-        
-        let result = A::from_archetype(self.entity_index, &self.archetype, &self.archetype_layout);
 
-        self.entity_index += 1;
+        let chunk_entities_count = (entities_count - chunk_start).min(entities_per_chunk_count);
+
+        let column = A::column(self.context, self.chunk_index);
+
+        let result = unsafe { A::slice_from_column(column, chunk_entities_count) };
+
+        self.chunk_index += 1;
 
         Some(result)
     }
@@ -157,28 +533,50 @@ pub struct Archetype {
 
 impl Archetype {
     pub fn new_from_components(components_set: UniqueComponentsSet) -> Self {
+        let layout = ArchetypeLayout::new_from_components(components_set);
+
         Self {
-            layout: Arc::new(ArchetypeLayout::new_from_components(components_set)),
-            archetype: UnsafeArchetype::new(),
+            archetype: UnsafeArchetype::new(layout.chunk_align()),
+            layout: Arc::new(layout),
             alive_entities_count: 0,
         }
     }
 
     pub fn contains_component_type<C: Component>(&self) -> bool {
-        self.layout.components().contains_key(&TypeId::of::<C>())
+        self.contains_component(&ComponentTypeId::of::<C>())
+    }
+
+    pub fn contains_component(&self, component_type: &ComponentTypeId) -> bool {
+        self.layout.components().contains_key(component_type)
     }
 
     pub fn components_set(&self) -> &UniqueComponentsSet {
         self.layout.components_set()
     }
 
-    pub fn iter<A: ArchetypeIteratorItem>(&self) -> ArchetypeIterator<A> {
+    pub fn iter<'a, A: ArchetypeIteratorItem>(&'a self, sparse_sets: &'a WorldSparseSets, ticks: RunTicks) -> ArchetypeIterator<'a, A> {
         // todo: threading guards
-        ArchetypeIterator::new(
-            &self.archetype,
-            Arc::clone(&self.layout),
-            self.alive_entities_count,
-        )
+        ArchetypeIterator::new(FetchContext {
+            archetype: self,
+            sparse_sets,
+            ticks,
+        })
+    }
+
+    pub fn iter_range<'a, A: ArchetypeIteratorItem>(&'a self, sparse_sets: &'a WorldSparseSets, ticks: RunTicks, range: Range<usize>) -> ArchetypeIterator<'a, A> {
+        ArchetypeIterator::new_in_range(FetchContext {
+            archetype: self,
+            sparse_sets,
+            ticks,
+        }, range)
+    }
+
+    pub fn iter_chunks<'a, A: ArchetypeIteratorItem>(&'a self, sparse_sets: &'a WorldSparseSets, ticks: RunTicks) -> ArchetypeChunksIterator<'a, A> {
+        ArchetypeChunksIterator::new(FetchContext {
+            archetype: self,
+            sparse_sets,
+            ticks,
+        })
     }
 
     pub fn entities_count(&self) -> usize {
@@ -221,19 +619,22 @@ impl Archetype {
     }
 
     unsafe fn get_component_ptr<C: Component>(&self, entity_index: usize) -> Option<*mut ()> {
+        self.get_component_ptr_by_id(entity_index, &ComponentTypeId::of::<C>())
+    }
+
+    /// Writing through the pointer requires the same exclusive access as mutating the component.
+    pub fn get_component_ptr_by_id(&self, entity_index: usize, component_type: &ComponentTypeId) -> Option<*mut ()> {
         if entity_index >= self.alive_entities_count {
             return None;
         }
 
-        let component_type = TypeId::of::<C>();
-
-        if !self.layout.components().contains_key(&component_type) {
+        if !self.layout.components().contains_key(component_type) {
             return None;
         }
 
-        let physical_location = self.layout.component_memory_physical_location(entity_index, &component_type);
+        let physical_location = self.layout.component_memory_physical_location(entity_index, component_type);
         
-        Some(self.archetype.get_memory(&physical_location).0)
+        Some(unsafe { self.archetype.get_memory(&physical_location).0 })
     }
 
     pub fn get_component_mut<C: Component>(&self, entity_index: usize) -> Option<&mut C> {
@@ -241,7 +642,7 @@ impl Archetype {
             return None;
         }
 
-        let component_type = TypeId::of::<C>();
+        let component_type = ComponentTypeId::of::<C>();
 
         if !self.layout.components().contains_key(&component_type) {
             return None;
@@ -257,6 +658,30 @@ impl Archetype {
         Some(component)
     }
 
+    pub fn get_component_ticks<C: Component>(&self, entity_index: usize) -> Option<ComponentTicks> {
+        unsafe { self.get_component_ticks_ptr::<C>(entity_index).map(|p| *p) }
+    }
+
+    /// Writing through the pointer requires the same exclusive access as mutating the component.
+    pub fn get_component_ticks_ptr<C: Component>(&self, entity_index: usize) -> Option<*mut ComponentTicks> {
+        self.get_component_ticks_ptr_by_id(entity_index, &ComponentTypeId::of::<C>())
+    }
+
+    /// Writing through the pointer requires the same exclusive access as mutating the component.
+    pub fn get_component_ticks_ptr_by_id(&self, entity_index: usize, component_type: &ComponentTypeId) -> Option<*mut ComponentTicks> {
+        if entity_index >= self.alive_entities_count {
+            return None;
+        }
+
+        if !self.layout.components().contains_key(component_type) {
+            return None;
+        }
+
+        let physical_location = self.layout.component_ticks_memory_physical_location(entity_index, component_type);
+
+        Some(unsafe { self.archetype.get_memory(&physical_location).0 as *mut ComponentTicks })
+    }
+
     fn create_place_for_entity(&mut self) -> usize {
         // todo: initialize components
         let entity_in_archetype_index = self.alive_entities_count;
@@ -272,6 +697,19 @@ impl Archetype {
         entity_in_archetype_index
     }
 
+    /// Allocates chunks for at least `additional` more entities.
+    pub fn reserve(&mut self, additional: usize) {
+        if additional == 0 {
+            return;
+        }
+
+        let chunks_count = self.layout.chunk_index(self.alive_entities_count + additional - 1) + 1;
+
+        while self.archetype.chunks_count() < chunks_count {
+            unsafe { self.archetype.push_chunk() };
+        }
+    }
+
     pub fn create_entity(&mut self, entity: Entity) {
         let entity_in_archetype_index = self.create_place_for_entity();
 
@@ -283,12 +721,164 @@ impl Archetype {
         }
     }
 
+    /// Allocates the rows of all the entities at once. Their components are left uninitialized.
+    pub fn create_entities(&mut self, entities: &[Entity]) {
+        let first_entity_index = self.alive_entities_count;
+
+        self.reserve(entities.len());
+        self.alive_entities_count += entities.len();
+
+        for (i, &entity) in entities.iter().enumerate() {
+            let entity_location = self.layout.entity_memory_physical_location(first_entity_index + i);
+
+            unsafe { *(self.archetype.get_memory(&entity_location).0 as *mut Entity) = entity };
+        }
+    }
+
+    /// Creates the entity with clones of the components of the row. Returns the new entity index.
+    /// `None` if the row does not exist or any of the components cannot be cloned.
+    pub fn clone_entity(&mut self, entity_index: usize, entity: Entity, tick: Tick) -> Option<usize> {
+        if entity_index >= self.alive_entities_count || !self.is_cloneable() {
+            return None;
+        }
+
+        let clone_index = self.alive_entities_count;
+
+        self.create_entity(entity);
+
+        for component_type in self.layout.components().keys() {
+            unsafe {
+                Self::clone_component(self, entity_index, self, clone_index, component_type);
+
+                *self.get_component_ticks_ptr_by_id(clone_index, component_type).unwrap() = ComponentTicks::new(tick);
+            }
+        }
+
+        Some(clone_index)
+    }
+
+    /// Clones all the entities with their component ticks. `None` if the archetype has entities and any of its components cannot be cloned.
+    pub fn try_clone(&self) -> Option<Self> {
+        if self.alive_entities_count > 0 && !self.is_cloneable() {
+            return None;
+        }
+
+        let mut clone = Self {
+            archetype: UnsafeArchetype::new(self.layout.chunk_align()),
+            layout: Arc::clone(&self.layout),
+            alive_entities_count: 0,
+        };
+
+        clone.reserve(self.alive_entities_count);
+
+        for entity_index in 0..self.alive_entities_count {
+            clone.create_entity(self.get_entity(entity_index).unwrap());
+
+            for component_type in self.layout.components().keys() {
+                unsafe {
+                    Self::clone_component(self, entity_index, &clone, entity_index, component_type);
+
+                    *clone.get_component_ticks_ptr_by_id(entity_index, component_type).unwrap() = *self.get_component_ticks_ptr_by_id(entity_index, component_type).unwrap();
+                }
+            }
+        }
+
+        Some(clone)
+    }
+
+    /// Whether every component of the archetype can be cloned.
+    pub fn is_cloneable(&self) -> bool {
+        self.layout.components().values().all(|c| c.type_info.cloner().is_some())
+    }
+
+    /// Clamps the component ticks of every entity with `Tick::check_tick`.
+    pub fn check_change_ticks(&mut self, tick: Tick) {
+        for entity_index in 0..self.alive_entities_count {
+            for component_type in self.layout.components().keys() {
+                unsafe { (*self.get_component_ticks_ptr_by_id(entity_index, component_type).unwrap()).check_ticks(tick) };
+            }
+        }
+    }
+
+    /// Passes the ticks of every entity component to `f`.
+    pub fn for_each_ticks_mut(&mut self, mut f: impl FnMut(Entity, &ComponentTypeId, &mut ComponentTicks)) {
+        for entity_index in 0..self.alive_entities_count {
+            let entity = self.get_entity(entity_index).unwrap();
+
+            for component_type in self.layout.components().keys() {
+                f(entity, component_type, unsafe { &mut *self.get_component_ticks_ptr_by_id(entity_index, component_type).unwrap() });
+            }
+        }
+    }
+
+    /// Drops all the entities. The chunks are kept for the next entities.
+    pub fn clear(&mut self) {
+        for entity_index in 0..self.alive_entities_count {
+            for (component_type, item_layout) in self.layout.components() {
+                unsafe { item_layout.type_info.drop(self.get_component_ptr_by_id(entity_index, component_type).unwrap()) };
+            }
+        }
+
+        self.alive_entities_count = 0;
+    }
+
+    /// # Safety
+    /// The `dst` component memory should be uninitialized, and the component should be cloneable.
+    unsafe fn clone_component(src: &Self, src_entity_index: usize, dst: &Self, dst_entity_index: usize, component_type: &ComponentTypeId) {
+        let cloner = src.layout.components()[component_type].type_info.cloner().unwrap();
+
+        cloner(
+            src.get_component_ptr_by_id(src_entity_index, component_type).unwrap(),
+            dst.get_component_ptr_by_id(dst_entity_index, component_type).unwrap(),
+        );
+    }
+
     /// Returns the last entity before the destroy.
     pub fn destroy_entity(&mut self, entity_index: usize) -> Option<Entity> {
         if entity_index >= self.alive_entities_count {
             return None;
         }
 
+        self.drop_row(entity_index);
+
+        self.erase_entity(entity_index)
+    }
+
+    /// Destroys the rows and fills the holes below the new entities count with the last rows, moving each row at most once.
+    /// Out of range and repeated indices are skipped. Returns the moved entities with their new indices.
+    pub fn destroy_entities(&mut self, entity_indices: &[usize]) -> Vec<(Entity, usize)> {
+        let mut entity_indices = entity_indices.iter()
+            .copied()
+            .filter(|&i| i < self.alive_entities_count)
+            .collect::<Vec<_>>();
+
+        entity_indices.sort_unstable();
+        entity_indices.dedup();
+
+        for &entity_index in &entity_indices {
+            self.drop_row(entity_index);
+        }
+
+        let remaining_count = self.alive_entities_count - entity_indices.len();
+
+        let (holes, destroyed_tail) = entity_indices.split_at(entity_indices.partition_point(|&i| i < remaining_count));
+
+        let sources = (remaining_count..self.alive_entities_count).filter(|i| destroyed_tail.binary_search(i).is_err());
+
+        let mut moved = Vec::with_capacity(holes.len());
+
+        for (&hole, source) in holes.iter().zip(sources) {
+            unsafe { Self::copy_row(self, source, self, hole, self.layout.components().keys()) };
+
+            moved.push((self.get_entity(hole).unwrap(), hole));
+        }
+
+        self.alive_entities_count = remaining_count;
+
+        moved
+    }
+
+    fn drop_row(&mut self, entity_index: usize) {
         for item_layout in self.layout.components().values() {
             let location = self.layout.component_memory_physical_location(entity_index, item_layout.type_info.id());
             unsafe {
@@ -296,8 +886,6 @@ impl Archetype {
                 item_layout.type_info.drop(memory.0)
             }
         }
-
-        self.erase_entity(entity_index)
     }
 
     fn erase_entity(&mut self, entity_index: usize) -> Option<Entity> {
@@ -309,17 +897,7 @@ impl Archetype {
         let last_index = self.alive_entities_count - 1;
 
         if entity_index != last_index {
-            
-            let last_components_locations = Self::get_items_locations_iter(&self.layout, last_index, &self.layout);
-            let entity_components_locations = Self::get_items_locations_iter(&self.layout, entity_index, &self.layout);
-
-            for (src_location, dst_location) in last_components_locations.zip(entity_components_locations) {
-                unsafe {
-                    let src_mem = self.archetype.get_memory(&src_location);
-                    let dst_mem = self.archetype.get_memory(&dst_location);
-                    std::ptr::copy_nonoverlapping(src_mem.0 as *mut u8, dst_mem.0 as *mut u8, dst_mem.1)
-                }
-            }
+            unsafe { Self::copy_row(self, last_index, self, entity_index, self.layout.components().keys()) };
         }
 
         let last_entity_location = self.layout.entity_memory_physical_location(last_index);
@@ -335,64 +913,89 @@ impl Archetype {
     }
 
     /// Returns the last entity from src archetype before the movement.
-    pub fn add_component<C: Component>(src: &mut Self, dst: &mut Self, src_entity_index: usize, component: C) -> Result<Entity, C> {
-        if !ArchetypeLayout::is_component_the_only_difference(&dst.layout, &src.layout, &TypeId::of::<C>()) {
+    pub fn add_component<C: Component>(src: &mut Self, dst: &mut Self, src_entity_index: usize, component: C, tick: Tick) -> Result<Entity, C> {
+        if !ArchetypeLayout::is_component_the_only_difference(&dst.layout, &src.layout, &ComponentTypeId::of::<C>()) {
             return Err(component);
         }
 
         let dst_entity_index = dst.create_place_for_entity();
 
-        let src_components_locations = Self::get_items_locations_iter(&src.layout, src_entity_index, &src.layout);
-        let dst_components_locations = Self::get_items_locations_iter(&src.layout, dst_entity_index, &dst.layout);
+        unsafe { Self::copy_row(src, src_entity_index, dst, dst_entity_index, src.layout.components().keys()) };
 
-        for (src_location, dst_location) in src_components_locations.zip(dst_components_locations) {
-            unsafe {
-                let src_mem = src.archetype.get_memory(&src_location);
-                let dst_mem = dst.archetype.get_memory(&dst_location);
-                std::ptr::copy_nonoverlapping(src_mem.0 as *mut u8, dst_mem.0 as *mut u8, dst_mem.1);
-            }
-        }
+        unsafe { dst.write_component(dst_entity_index, component, tick) };
 
-        let added_component_location = dst.layout.component_memory_physical_location(dst_entity_index, &TypeId::of::<C>());
 
-        unsafe {
-            let added_mem = dst.archetype.get_memory(&added_component_location);
-            *(added_mem.0 as *mut C) = component;
-        }
-        
         Ok(src.erase_entity(src_entity_index).unwrap())
     }
 
-    fn get_items_locations_iter<'l>(componens_layout: &'l ArchetypeLayout, entity_in_archetype_index: usize, memory_layout: &'l ArchetypeLayout) -> impl Iterator<Item = ArchetypeItemPhysicalLocation> + 'l {
-        componens_layout
-            .components()
-            .keys()
-            .map(move |t| memory_layout.component_memory_physical_location(entity_in_archetype_index.clone(), &t.clone()))
-            .chain(std::iter::once(memory_layout.entity_memory_physical_location(entity_in_archetype_index.clone())))
+    /// Moves the entity row into `dst` copying the components both archetypes have.
+    /// Returns the entity index in `dst` and the last entity from `src` before the movement.
+    ///
+    /// # Safety
+    /// Nothing is dropped: components only `src` has should be read out beforehand, components only `dst` has should be written afterwards.
+    pub unsafe fn move_entity(src: &mut Self, dst: &mut Self, src_entity_index: usize) -> (usize, Entity) {
+        let dst_entity_index = dst.create_place_for_entity();
+
+        let dst_components = dst.layout.components();
+        let common_components = src.layout.components().keys().filter(|c| dst_components.contains_key(c));
+
+        Self::copy_row(src, src_entity_index, dst, dst_entity_index, common_components);
+
+        (dst_entity_index, src.erase_entity(src_entity_index).unwrap())
+    }
+
+    /// # Safety
+    /// The component memory should be uninitialized, otherwise the previous value leaks.
+    pub unsafe fn write_component<C: Component>(&self, entity_index: usize, component: C, tick: Tick) {
+        (self.get_component_ptr::<C>(entity_index).unwrap() as *mut C).write(component);
+        *self.get_component_ticks_ptr::<C>(entity_index).unwrap() = ComponentTicks::new(tick);
+    }
+
+    /// Bitwise moves the component out of the row.
+    ///
+    /// # Safety
+    /// The component memory should not be used until it is written again or the row is erased.
+    pub unsafe fn read_component<C: Component>(&self, entity_index: usize) -> C {
+        (self.get_component_ptr::<C>(entity_index).unwrap() as *const C).read()
+    }
+
+    /// Bitwise copies the entity and the given components with their ticks from the `src` row into the `dst` row column by column.
+    /// Both archetypes should contain all the given components.
+    unsafe fn copy_row<'c>(src: &Self, src_entity_index: usize, dst: &Self, dst_entity_index: usize, components: impl Iterator<Item = &'c ComponentTypeId>) {
+        let items_locations = components
+            .flat_map(|c| [
+                (
+                    src.layout.component_memory_physical_location(src_entity_index, c),
+                    dst.layout.component_memory_physical_location(dst_entity_index, c),
+                ),
+                (
+                    src.layout.component_ticks_memory_physical_location(src_entity_index, c),
+                    dst.layout.component_ticks_memory_physical_location(dst_entity_index, c),
+                ),
+            ])
+            .chain(std::iter::once((
+                src.layout.entity_memory_physical_location(src_entity_index),
+                dst.layout.entity_memory_physical_location(dst_entity_index),
+            )));
+
+        for (src_location, dst_location) in items_locations {
+            let src_mem = src.archetype.get_memory(&src_location);
+            let dst_mem = dst.archetype.get_memory(&dst_location);
+            std::ptr::copy_nonoverlapping(src_mem.0 as *const u8, dst_mem.0 as *mut u8, dst_mem.1);
+        }
     }
 
     /// Returns the last entity from src archetype before the movement.
     pub fn remove_component<C: Component>(src: &mut Self, dst: &mut Self, src_entity_index: usize) -> Option<(Entity, C)> {
-        if !ArchetypeLayout::is_component_the_only_difference(&src.layout, &dst.layout, &TypeId::of::<C>()) {
+        if !ArchetypeLayout::is_component_the_only_difference(&src.layout, &dst.layout, &ComponentTypeId::of::<C>()) {
             return None;
         }
 
         let dst_entity_index = dst.create_place_for_entity();
 
-        let src_components_locations = Self::get_items_locations_iter(&dst.layout, src_entity_index, &src.layout);
-        let dst_components_locations = Self::get_items_locations_iter(&dst.layout, dst_entity_index, &dst.layout);
-
-        for (src_location, dst_location) in src_components_locations.zip(dst_components_locations) {
-            unsafe {
-                let src_mem = src.archetype.get_memory(&src_location);
-                let dst_mem = dst.archetype.get_memory(&dst_location);
-                std::ptr::copy_nonoverlapping(src_mem.0 as *mut u8, dst_mem.0 as *mut u8, dst_mem.1)
-            }
-        }
+        unsafe { Self::copy_row(src, src_entity_index, dst, dst_entity_index, dst.layout.components().keys()) };
 
-        let component_ref = src.get_component_ref::<C>(src_entity_index).unwrap();
-
-        let component = unsafe { std::mem::transmute_copy(component_ref) };
+        let component = unsafe { (src.get_component_ptr::<C>(src_entity_index).unwrap() as *const C).read() };
 
         Some((src.erase_entity(src_entity_index).unwrap(), component))
     }
@@ -404,4 +1007,97 @@ impl Archetype {
     pub unsafe fn unsafe_archetype(&self) -> &UnsafeArchetype {
         &self.archetype
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use crate::world_entities_components::WorldEntitiesComponents;
+
+    use super::*;
+
+    struct Position(f32);
+    impl Component for Position { }
+
+    struct Velocity(f32);
+    impl Component for Velocity { }
+
+    static DROPPED_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    struct DropCounter;
+    impl Component for DropCounter { }
+
+    impl Drop for DropCounter {
+        fn drop(&mut self) {
+            DROPPED_COUNT.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn component_column_is_contiguous_within_chunk() {
+        let mut entities_components = WorldEntitiesComponents::new();
+
+        for i in 0..100 {
+            let entity = entities_components.create_entity();
+            entities_components.add_component(entity, Position(i as f32));
+            entities_components.add_component(entity, Velocity(1.0));
+        }
+
+        let query = entities_components.query::<&Position>();
+        let positions = query.iter().collect::<Vec<_>>();
+
+        for pair in positions.windows(2) {
+            let stride = pair[1] as *const Position as usize - pair[0] as *const Position as usize;
+            assert_eq!(stride, std::mem::size_of::<Position>());
+        }
+    }
+
+    #[test]
+    fn moving_between_archetypes_keeps_values() {
+        let mut entities_components = WorldEntitiesComponents::new();
+
+        let entities = (0..1000).map(|i| {
+            let entity = entities_components.create_entity();
+            entities_components.add_component(entity, Position(i as f32));
+            entity
+        }).collect::<Vec<_>>();
+
+        for &entity in entities.iter().step_by(2) {
+            entities_components.add_component(entity, Velocity(2.0));
+        }
+
+        for &entity in entities.iter().step_by(4) {
+            assert_eq!(entities_components.remove_component::<Velocity>(entity).map(|v| v.0), Some(2.0));
+        }
+
+        for (i, &entity) in entities.iter().enumerate() {
+            assert_eq!(entities_components.get_component::<Position>(entity).unwrap().0, i as f32);
+            assert_eq!(entities_components.get_component::<Velocity>(entity).is_some(), i % 2 == 0 && i % 4 != 0);
+        }
+    }
+
+    #[test]
+    fn moved_components_are_dropped_once() {
+        let mut entities_components = WorldEntitiesComponents::new();
+
+        let entities = (0..10).map(|_| entities_components.create_entity()).collect::<Vec<_>>();
+
+        for &entity in entities.iter() {
+            entities_components.add_component(entity, DropCounter);
+            entities_components.add_component(entity, Position(0.0));
+        }
+
+        for &entity in entities.iter() {
+            entities_components.remove_component::<Position>(entity);
+            entities_components.add_component(entity, Velocity(0.0));
+        }
+
+        assert_eq!(DROPPED_COUNT.load(Ordering::SeqCst), 0);
+
+        for &entity in entities.iter() {
+            entities_components.destroy_entity(entity);
+        }
+
+        assert_eq!(DROPPED_COUNT.load(Ordering::SeqCst), entities.len());
+    }
+}
@@ -1,49 +1,85 @@
-use std::{
-    any::TypeId,
-    collections::HashMap
-};
+use std::collections::HashMap;
 
 use super::{
-    entity::Entity, type_info::TypeInfo, unique_components_set::UniqueComponentsSet, unsafe_archetype::{
+    change_detection::ComponentTicks, entity::Entity, type_info::{ComponentTypeId, TypeInfo}, unique_components_set::UniqueComponentsSet, unsafe_archetype::{
         ArchetypeItemPhysicalLocation,
+        CHUNK_MIN_ALIGN,
         CHUNK_SIZE,
     }
 };
 
-// todo (critical): account for alignment (now it only works safe on Intel CPU)
-
+/// Chunk memory is stored as columns: every item type of the archetype has one contiguous
+/// array of `entities_per_chunk_count` elements per chunk, starting with the entity column.
+/// Component columns are followed by the component ticks columns.
 pub struct ArchetypeItemLayout {
     pub type_info: TypeInfo,
+    /// Offset of the item column from the start of a chunk.
     pub offset: usize,
+    /// Offset of the item ticks column from the start of a chunk. Unused for the entity column.
+    pub ticks_offset: usize,
     pub order: usize,
 }
 
 pub struct ArchetypeLayout {
     components_set: UniqueComponentsSet,
-    components: HashMap<TypeId, ArchetypeItemLayout>,
+    components: HashMap<ComponentTypeId, ArchetypeItemLayout>,
     entity_size: usize,
+    entities_per_chunk_count: usize,
+    chunk_align: usize,
 }
 
 impl ArchetypeLayout {
     pub fn new_from_components(components_set: UniqueComponentsSet) -> Self {
-        let mut components = HashMap::new();
+        let entity_info = TypeInfo::new::<Entity>();
+        let ticks_info = TypeInfo::new::<ComponentTicks>();
+
+        let components_count = components_set.component_infos().len();
+        let items_infos = || std::iter::once(&entity_info)
+            .chain(components_set.component_infos().values())
+            .chain(std::iter::repeat_n(&ticks_info, components_count));
+
+        let entity_size = items_infos().map(|i| i.size()).sum::<usize>();
+        // every column may need up to (align - 1) bytes of padding before it.
+        let max_padding = items_infos().map(|i| i.align() - 1).sum::<usize>();
+        let chunk_align = items_infos().map(|i| i.align()).fold(CHUNK_MIN_ALIGN, usize::max);
+
+        let entities_per_chunk_count = CHUNK_SIZE.saturating_sub(max_padding) / entity_size;
 
-        let mut offset = std::mem::size_of::<Entity>();
+        assert!(entities_per_chunk_count > 0, "Archetype entity does not fit into a chunk. Entity size: {entity_size}.");
 
-        for (order, (&id, &type_info)) in components_set.component_infos().iter().enumerate() {
-            components.insert(id, ArchetypeItemLayout {
+        let mut offset = entities_per_chunk_count * entity_info.size();
+
+        let mut components = components_set.component_infos().iter().enumerate().map(|(order, (&id, &type_info))| {
+            offset = offset.next_multiple_of(type_info.align());
+
+            let item_layout = ArchetypeItemLayout {
                 offset,
+                ticks_offset: 0,
                 type_info,
                 order: order + 1,
-            });
+            };
+
+            offset += entities_per_chunk_count * type_info.size();
+
+            (id, item_layout)
+        }).collect::<HashMap<_, _>>();
 
-            offset += type_info.size();
+        for item_layout in components.values_mut() {
+            offset = offset.next_multiple_of(ticks_info.align());
+
+            item_layout.ticks_offset = offset;
+
+            offset += entities_per_chunk_count * ticks_info.size();
         }
 
+        debug_assert!(offset <= CHUNK_SIZE);
+
         Self {
             components_set,
             components,
-            entity_size: offset,
+            entity_size,
+            entities_per_chunk_count,
+            chunk_align,
         }
     }
 
@@ -54,12 +90,13 @@ impl ArchetypeLayout {
     pub fn entity_item_layout() -> ArchetypeItemLayout {
         ArchetypeItemLayout {
             offset: 0,
+            ticks_offset: 0,
             order: 0,
             type_info: TypeInfo::new::<Entity>(),
         }
     }
 
-    pub fn components(&self) -> &HashMap<TypeId, ArchetypeItemLayout> {
+    pub fn components(&self) -> &HashMap<ComponentTypeId, ArchetypeItemLayout> {
         &self.components
     }
 
@@ -68,10 +105,15 @@ impl ArchetypeLayout {
     }
 
     pub fn entities_per_chunk_count(&self) -> usize {
-        CHUNK_SIZE / self.entity_size
+        self.entities_per_chunk_count
+    }
+
+    /// Alignment of a chunk start, satisfies the alignment of every item in the archetype.
+    pub fn chunk_align(&self) -> usize {
+        self.chunk_align
     }
     
-    pub fn is_component_the_only_difference(with_component: &Self, without_component: &Self, component: &TypeId) -> bool {
+    pub fn is_component_the_only_difference(with_component: &Self, without_component: &Self, component: &ComponentTypeId) -> bool {
         let with_component = with_component.components();
         let without_component = without_component.components();
         
@@ -106,7 +148,7 @@ impl ArchetypeLayout {
         entity_in_archetype_index % self.entities_per_chunk_count()
     }
 
-    pub fn component_memory_physical_location(&self, entity_in_archetype_index: usize, component: &TypeId) -> ArchetypeItemPhysicalLocation {
+    pub fn component_memory_physical_location(&self, entity_in_archetype_index: usize, component: &ComponentTypeId) -> ArchetypeItemPhysicalLocation {
         let item_layout = self.components.get(component).unwrap();
 
         self.memory_physical_location(entity_in_archetype_index, item_layout)
@@ -118,19 +160,136 @@ impl ArchetypeLayout {
         self.memory_physical_location(entity_in_archetype_index, &item_layout)
     }
 
+    pub fn component_column_physical_location(&self, chunk_index: usize, component: &ComponentTypeId) -> ArchetypeItemPhysicalLocation {
+        let item_layout = self.components.get(component).unwrap();
+
+        self.column_physical_location(chunk_index, item_layout)
+    }
+
+    pub fn entity_column_physical_location(&self, chunk_index: usize) -> ArchetypeItemPhysicalLocation {
+        let item_layout = ArchetypeLayout::entity_item_layout();
+
+        self.column_physical_location(chunk_index, &item_layout)
+    }
+
+    pub fn component_ticks_memory_physical_location(&self, entity_in_archetype_index: usize, component: &ComponentTypeId) -> ArchetypeItemPhysicalLocation {
+        let item_layout = self.components.get(component).unwrap();
+
+        self.memory_physical_location(entity_in_archetype_index, &Self::ticks_item_layout(item_layout))
+    }
+
+    pub fn component_ticks_column_physical_location(&self, chunk_index: usize, component: &ComponentTypeId) -> ArchetypeItemPhysicalLocation {
+        let item_layout = self.components.get(component).unwrap();
+
+        self.column_physical_location(chunk_index, &Self::ticks_item_layout(item_layout))
+    }
+
+    fn ticks_item_layout(item_layout: &ArchetypeItemLayout) -> ArchetypeItemLayout {
+        ArchetypeItemLayout {
+            offset: item_layout.ticks_offset,
+            ticks_offset: 0,
+            order: item_layout.order,
+            type_info: TypeInfo::new::<ComponentTicks>(),
+        }
+    }
+
+    fn column_physical_location(&self, chunk_index: usize, item_layout: &ArchetypeItemLayout) -> ArchetypeItemPhysicalLocation {
+        ArchetypeItemPhysicalLocation {
+            chunk_index,
+            memory_offset: item_layout.offset,
+            memory_size: self.entities_per_chunk_count * item_layout.type_info.size(),
+            memory_align: item_layout.type_info.align(),
+        }
+    }
+
     fn memory_physical_location(&self, entity_in_archetype_index: usize, item_layout: &ArchetypeItemLayout) -> ArchetypeItemPhysicalLocation {
         let entity_in_chunk_index = self.entity_in_chunk_index(entity_in_archetype_index);
 
-        let components_line_offset = self.entities_per_chunk_count() * item_layout.offset;
-
         let memory_size = item_layout.type_info.size();
 
-        let memory_offset = components_line_offset + entity_in_chunk_index * memory_size;
+        let memory_offset = item_layout.offset + entity_in_chunk_index * memory_size;
 
         ArchetypeItemPhysicalLocation {
             chunk_index: self.chunk_index(entity_in_archetype_index),
             memory_offset,
             memory_size,
+            memory_align: item_layout.type_info.align(),
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use crate::{component::Component, world_entities_components::WorldEntitiesComponents};
+
+    use super::*;
+
+    struct Byte(u8);
+    impl Component for Byte { }
+
+    struct Wide(u64);
+    impl Component for Wide { }
+
+    #[repr(align(128))]
+    struct OverAligned(u8);
+    impl Component for OverAligned { }
+
+    fn layout_of_byte_wide_over_aligned() -> ArchetypeLayout {
+        let mut components_set = UniqueComponentsSet::new();
+        components_set.insert::<Byte>();
+        components_set.insert::<Wide>();
+        components_set.insert::<OverAligned>();
+
+        ArchetypeLayout::new_from_components(components_set)
+    }
+
+    #[test]
+    fn component_columns_are_aligned() {
+        let layout = layout_of_byte_wide_over_aligned();
+
+        for item_layout in layout.components().values() {
+            assert_eq!(item_layout.offset % item_layout.type_info.align(), 0, "{}", item_layout.type_info.name());
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn component_columns_fit_into_chunk() {
+        let layout = layout_of_byte_wide_over_aligned();
+
+        let entities_count = layout.entities_per_chunk_count();
+
+        for item_layout in layout.components().values() {
+            assert!(item_layout.offset + entities_count * item_layout.type_info.size() <= CHUNK_SIZE);
+        }
+    }
+
+    #[test]
+    fn chunk_align_covers_every_component() {
+        let layout = layout_of_byte_wide_over_aligned();
+
+        assert_eq!(layout.chunk_align(), 128);
+    }
+
+    #[test]
+    fn stored_components_are_aligned_in_memory() {
+        let mut entities_components = WorldEntitiesComponents::new();
+
+        let entities = (0..300).map(|_| entities_components.create_entity()).collect::<Vec<_>>();
+
+        for (i, &entity) in entities.iter().enumerate() {
+            entities_components.add_component(entity, Byte(i as u8));
+            entities_components.add_component(entity, Wide(i as u64));
+            entities_components.add_component(entity, OverAligned(i as u8));
+        }
+
+        for (i, &entity) in entities.iter().enumerate() {
+            let wide = entities_components.get_component::<Wide>(entity).unwrap();
+            let over_aligned = entities_components.get_component::<OverAligned>(entity).unwrap();
+
+            assert_eq!(wide as *const Wide as usize % std::mem::align_of::<Wide>(), 0);
+            assert_eq!(over_aligned as *const OverAligned as usize % 128, 0);
+            assert_eq!(wide.0, i as u64);
+            assert_eq!(over_aligned.0, i as u8);
+            assert_eq!(entities_components.get_component::<Byte>(entity).unwrap().0, i as u8);
+        }
+    }
+}
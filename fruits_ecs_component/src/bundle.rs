@@ -0,0 +1,131 @@
+use std::{any::TypeId, collections::HashSet};
+
+use super::{
+    archetype::Archetype,
+    change_detection::Tick,
+    command_queue::CommandQueue,
+    component::{Component, StorageType},
+    entity::Entity,
+    required_components::RequiredComponents,
+    sparse_set::WorldSparseSets,
+    unique_components_set::UniqueComponentsSet,
+};
+
+/// A set of components added to or removed from an entity in one archetype move.
+///
+/// # Safety
+/// `write` and `read` should touch exactly the components registered in `fill_components`.
+pub unsafe trait Bundle: 'static {
+    /// Registers the table components into `table_components` and the sparse set ones into `sparse_components`.
+    /// Panics if the bundle contains the same component twice.
+    fn fill_components(table_components: &mut UniqueComponentsSet, sparse_components: &mut HashSet<TypeId>);
+
+    fn contains_any(archetype: &Archetype, entity: Entity, sparse_sets: &WorldSparseSets) -> bool;
+    fn contains_all(archetype: &Archetype, entity: Entity, sparse_sets: &WorldSparseSets) -> bool;
+
+    /// Components are marked as added at `tick`.
+    ///
+    /// # Safety
+    /// The entity row should be alive in the archetype and its bundle components should be uninitialized.
+    unsafe fn write(self, archetype: &Archetype, entity_index: usize, entity: Entity, sparse_sets: &mut WorldSparseSets, tick: Tick);
+
+    /// Moves the bundle components out. Table components are left uninitialized in the row.
+    ///
+    /// # Safety
+    /// The entity should contain all the bundle components.
+    unsafe fn read(archetype: &Archetype, entity_index: usize, entity: Entity, sparse_sets: &mut WorldSparseSets) -> Self;
+
+    /// Calls `on_add` and then `on_insert` hooks of the bundle components.
+    fn on_add(entity: Entity, commands: &mut CommandQueue);
+    fn on_remove(entity: Entity, commands: &mut CommandQueue);
+
+    fn register_required(required: &mut RequiredComponents);
+}
+
+fn fill_component<C: Component>(table_components: &mut UniqueComponentsSet, sparse_components: &mut HashSet<TypeId>) {
+    let is_unique = match C::STORAGE_TYPE {
+        StorageType::Table => table_components.insert::<C>(),
+        StorageType::SparseSet => sparse_components.insert(TypeId::of::<C>()),
+    };
+
+    assert!(is_unique, "Bundle contains duplicate components.");
+}
+
+fn contains_component<C: Component>(archetype: &Archetype, entity: Entity, sparse_sets: &WorldSparseSets) -> bool {
+    match C::STORAGE_TYPE {
+        StorageType::Table => archetype.contains_component_type::<C>(),
+        StorageType::SparseSet => sparse_sets.get::<C>().is_some_and(|s| s.contains(entity)),
+    }
+}
+
+unsafe fn write_component<C: Component>(archetype: &Archetype, entity_index: usize, entity: Entity, sparse_sets: &mut WorldSparseSets, component: C, tick: Tick) {
+    match C::STORAGE_TYPE {
+        StorageType::Table => archetype.write_component(entity_index, component, tick),
+        StorageType::SparseSet => { sparse_sets.get_or_create_mut::<C>().insert(entity, component, tick); },
+    }
+}
+
+unsafe fn read_component<C: Component>(archetype: &Archetype, entity_index: usize, entity: Entity, sparse_sets: &mut WorldSparseSets) -> C {
+    match C::STORAGE_TYPE {
+        StorageType::Table => archetype.read_component(entity_index),
+        StorageType::SparseSet => sparse_sets.get_mut::<C>().unwrap().remove(entity).unwrap(),
+    }
+}
+
+macro_rules! bundle_impl {
+    ($($P: ident),+) => {
+        unsafe impl<$($P: Component),+> Bundle for ($($P,)+) {
+            fn fill_components(table_components: &mut UniqueComponentsSet, sparse_components: &mut HashSet<TypeId>) {
+                $(fill_component::<$P>(table_components, sparse_components);)+
+            }
+
+            fn contains_any(archetype: &Archetype, entity: Entity, sparse_sets: &WorldSparseSets) -> bool {
+                $(contains_component::<$P>(archetype, entity, sparse_sets))||+
+            }
+
+            fn contains_all(archetype: &Archetype, entity: Entity, sparse_sets: &WorldSparseSets) -> bool {
+                $(contains_component::<$P>(archetype, entity, sparse_sets))&&+
+            }
+
+            unsafe fn write(self, archetype: &Archetype, entity_index: usize, entity: Entity, sparse_sets: &mut WorldSparseSets, tick: Tick) {
+                #[allow(non_snake_case)]
+                let ($($P,)+) = self;
+
+                $(write_component(archetype, entity_index, entity, sparse_sets, $P, tick);)+
+            }
+
+            unsafe fn read(archetype: &Archetype, entity_index: usize, entity: Entity, sparse_sets: &mut WorldSparseSets) -> Self {
+                ($(read_component::<$P>(archetype, entity_index, entity, sparse_sets),)+)
+            }
+
+            fn on_add(entity: Entity, commands: &mut CommandQueue) {
+                $($P::on_add(entity, commands);)+
+                $($P::on_insert(entity, commands);)+
+            }
+
+            fn on_remove(entity: Entity, commands: &mut CommandQueue) {
+                $($P::on_remove(entity, commands);)+
+            }
+
+            fn register_required(required: &mut RequiredComponents) {
+                $($P::register_required(required);)+
+            }
+        }
+    };
+}
+
+bundle_impl!(P0);
+bundle_impl!(P0, P1);
+bundle_impl!(P0, P1, P2);
+bundle_impl!(P0, P1, P2, P3);
+bundle_impl!(P0, P1, P2, P3, P4);
+bundle_impl!(P0, P1, P2, P3, P4, P5);
+bundle_impl!(P0, P1, P2, P3, P4, P5, P6);
+bundle_impl!(P0, P1, P2, P3, P4, P5, P6, P7);
+bundle_impl!(P0, P1, P2, P3, P4, P5, P6, P7, P8);
+bundle_impl!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9);
+bundle_impl!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9, P10);
+bundle_impl!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9, P10, P11);
+bundle_impl!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9, P10, P11, P12);
+bundle_impl!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9, P10, P11, P12, P13);
+bundle_impl!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9, P10, P11, P12, P13, P14);
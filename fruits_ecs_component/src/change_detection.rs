@@ -0,0 +1,235 @@
+use std::{any::TypeId, collections::HashSet, marker::PhantomData};
+
+use fruits_ecs_data_usage::{DataUsageEntry, PerTypeDataUsage};
+
+use super::{
+    archetype::{Archetype, ComponentColumn, FetchContext},
+    component::Component,
+    disabled::is_disabled_marker,
+    query_filter::QueryFilter,
+};
+
+/// How many ticks may pass between two `WorldEntitiesComponents::check_change_ticks` passes.
+pub const CHECK_TICK_THRESHOLD: u32 = 518_400_000;
+
+/// Ticks older than this are clamped, so they keep looking old until the counter wraps around them.
+pub const MAX_CHANGE_AGE: u32 = u32::MAX - (2 * CHECK_TICK_THRESHOLD - 1);
+
+/// A moment of the world change history. Every system run gets a new one.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Tick(u32);
+
+impl Tick {
+    pub const fn new(tick: u32) -> Self {
+        Self(tick)
+    }
+
+    pub const fn get(self) -> u32 {
+        self.0
+    }
+
+    /// Whether the tick happened after `last_run` and not after `this_run`. Ticks older than `MAX_CHANGE_AGE` count as that old.
+    /// Survives the counter wrapping only if both ticks are clamped with `check_tick` at least every `CHECK_TICK_THRESHOLD` ticks.
+    pub fn is_newer_than(self, last_run: Tick, this_run: Tick) -> bool {
+        let ticks_since_self = this_run.0.wrapping_sub(self.0).min(MAX_CHANGE_AGE);
+        let ticks_since_last_run = this_run.0.wrapping_sub(last_run.0).min(MAX_CHANGE_AGE);
+
+        ticks_since_self < ticks_since_last_run
+    }
+
+    /// Moves the tick forward to be at most `MAX_CHANGE_AGE` older than `tick`.
+    pub fn check_tick(&mut self, tick: Tick) {
+        if tick.0.wrapping_sub(self.0) > MAX_CHANGE_AGE {
+            self.0 = tick.0.wrapping_sub(MAX_CHANGE_AGE);
+        }
+    }
+
+    /// Whether the tick happened before `other`. Both ticks should be less than `u32::MAX / 2` apart.
+    pub fn is_older_than(self, other: Tick) -> bool {
+        (other.0.wrapping_sub(self.0) as i32) > 0
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct ComponentTicks {
+    pub added: Tick,
+    pub changed: Tick,
+}
+
+impl ComponentTicks {
+    pub const fn new(tick: Tick) -> Self {
+        Self {
+            added: tick,
+            changed: tick,
+        }
+    }
+
+    pub fn check_ticks(&mut self, tick: Tick) {
+        self.added.check_tick(tick);
+        self.changed.check_tick(tick);
+    }
+}
+
+/// Ticks a query compares component ticks against.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct RunTicks {
+    /// The tick of the previous run of the system. Changes made after it are visible.
+    pub last_run: Tick,
+    /// The tick of the current run of the system. Mutable access marks components with it.
+    pub this_run: Tick,
+}
+
+/// Filters entities whose `C` component was added since the last run of the system.
+pub struct Added<C: Component>(PhantomData<C>);
+
+/// Filters entities whose `C` component was added or mutably accessed since the last run of the system.
+pub struct Changed<C: Component>(PhantomData<C>);
+
+unsafe impl<C: Component> QueryFilter for Added<C> {
+    type Column<'w> = (ComponentColumn<'w, C>, RunTicks);
+
+    fn column<'w>(context: FetchContext<'w>, chunk_index: usize) -> Self::Column<'w> {
+        (ComponentColumn::new(context, chunk_index), context.ticks)
+    }
+
+    unsafe fn matches_row<'w>((column, ticks): Self::Column<'w>, entity_in_chunk_index: usize) -> bool {
+        column.contains(entity_in_chunk_index)
+            && (*column.get_ticks(entity_in_chunk_index)).added.is_newer_than(ticks.last_run, ticks.this_run)
+    }
+
+    fn fill_usage(usage: &mut PerTypeDataUsage) {
+        usage.add(DataUsageEntry::new_readonly(TypeId::of::<C>()));
+    }
+
+    fn fill_archetype_components(components: &mut HashSet<TypeId>) {
+        ComponentColumn::<C>::fill_archetype_components(components);
+    }
+
+    fn matches_archetype(archetype: &Archetype) -> bool {
+        ComponentColumn::<C>::matches_archetype(archetype)
+    }
+
+    const IS_DENSE: bool = false;
+
+    fn includes_disabled() -> bool {
+        is_disabled_marker::<C>()
+    }
+}
+
+unsafe impl<C: Component> QueryFilter for Changed<C> {
+    type Column<'w> = (ComponentColumn<'w, C>, RunTicks);
+
+    fn column<'w>(context: FetchContext<'w>, chunk_index: usize) -> Self::Column<'w> {
+        (ComponentColumn::new(context, chunk_index), context.ticks)
+    }
+
+    unsafe fn matches_row<'w>((column, ticks): Self::Column<'w>, entity_in_chunk_index: usize) -> bool {
+        column.contains(entity_in_chunk_index)
+            && (*column.get_ticks(entity_in_chunk_index)).changed.is_newer_than(ticks.last_run, ticks.this_run)
+    }
+
+    fn fill_usage(usage: &mut PerTypeDataUsage) {
+        usage.add(DataUsageEntry::new_readonly(TypeId::of::<C>()));
+    }
+
+    fn fill_archetype_components(components: &mut HashSet<TypeId>) {
+        ComponentColumn::<C>::fill_archetype_components(components);
+    }
+
+    fn matches_archetype(archetype: &Archetype) -> bool {
+        ComponentColumn::<C>::matches_archetype(archetype)
+    }
+
+    const IS_DENSE: bool = false;
+
+    fn includes_disabled() -> bool {
+        is_disabled_marker::<C>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{component::StorageType, entity::Entity, world_entities_components::WorldEntitiesComponents};
+
+    use super::*;
+
+    struct Position(u32);
+    impl Component for Position { }
+
+    struct Tag;
+    impl Component for Tag {
+        const STORAGE_TYPE: StorageType = StorageType::SparseSet;
+    }
+
+    #[test]
+    fn tick_comparison_survives_wrapping() {
+        let last_run = Tick::new(u32::MAX - 1);
+        let this_run = Tick::new(3);
+
+        assert!(Tick::new(u32::MAX).is_newer_than(last_run, this_run));
+        assert!(Tick::new(3).is_newer_than(last_run, this_run));
+        assert!(!Tick::new(u32::MAX - 1).is_newer_than(last_run, this_run));
+        assert!(!Tick::new(4).is_newer_than(last_run, this_run));
+    }
+
+    #[test]
+    fn checked_ticks_are_not_newer_after_wrapping() {
+        let mut tick = Tick::new(1);
+        let mut this_run = tick;
+
+        for _ in 0..8 {
+            this_run = Tick::new(this_run.get().wrapping_add(CHECK_TICK_THRESHOLD));
+            tick.check_tick(this_run);
+        }
+
+        // The counter wrapped past the tick: 2^32 + 5 ticks have passed since it.
+        let this_run = Tick::new(6);
+        let last_run = Tick::new(this_run.get().wrapping_sub(10));
+
+        tick.check_tick(this_run);
+
+        assert!(Tick::new(1).is_newer_than(last_run, this_run));
+        assert!(!tick.is_newer_than(last_run, this_run));
+        assert!(Tick::new(5).is_newer_than(last_run, this_run));
+    }
+
+    #[test]
+    fn added_and_changed_filters_see_changes_since_last_run() {
+        let mut entities_components = WorldEntitiesComponents::new();
+
+        let entities = entities_components.spawn_batch((0..10).map(|i| (Position(i), Tag)));
+
+        let reader_first_run = RunTicks { last_run: Tick::default(), this_run: entities_components.increment_change_tick() };
+
+        assert_eq!(entities_components.query_filtered::<Entity, Added<Position>>(reader_first_run).len(), 10);
+        assert_eq!(entities_components.query_filtered::<Entity, Changed<Tag>>(reader_first_run).len(), 10);
+
+        let writer_run = RunTicks { last_run: Tick::default(), this_run: entities_components.increment_change_tick() };
+
+        {
+            let mut query = entities_components.query_filtered::<&mut Position, ()>(writer_run);
+
+            for &entity in entities.iter().step_by(2) {
+                query.get_mut(entity).unwrap().0 += 1;
+            }
+        }
+
+        entities_components.remove_component::<Tag>(entities[9]);
+        entities_components.add_component(entities[9], Tag);
+
+        let reader_second_run = RunTicks { last_run: reader_first_run.this_run, this_run: entities_components.increment_change_tick() };
+
+        assert_eq!(entities_components.query_filtered::<Entity, Added<Position>>(reader_second_run).len(), 0);
+        assert_eq!(entities_components.query_filtered::<Entity, Changed<Position>>(reader_second_run).len(), 5);
+        assert_eq!(entities_components.query_filtered::<Entity, Added<Tag>>(reader_second_run).get(entities[9]), Some(entities[9]));
+        assert_eq!(entities_components.query_filtered::<Entity, Added<Tag>>(reader_second_run).get(entities[8]), None);
+
+        entities_components.get_component_mut::<Tag>(entities[1]);
+
+        let reader_third_run = RunTicks { last_run: reader_second_run.this_run, this_run: entities_components.increment_change_tick() };
+
+        assert_eq!(entities_components.query_filtered::<Entity, Changed<Position>>(reader_third_run).len(), 0);
+        assert_eq!(entities_components.query_filtered::<Entity, (Changed<Tag>, Changed<Position>)>(reader_third_run).len(), 0);
+        assert_eq!(entities_components.query_filtered::<Entity, Changed<Tag>>(reader_third_run).iter().collect::<Vec<_>>(), [entities[1]]);
+    }
+}
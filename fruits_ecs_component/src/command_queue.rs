@@ -0,0 +1,70 @@
+use super::{bundle::Bundle, component::Component, entity::Entity, world_entities_components::WorldEntitiesComponents};
+
+type Command = Box<dyn FnOnce(&mut WorldEntitiesComponents) + Send>;
+
+/// Structural changes recorded to be applied to the world later.
+#[derive(Default)]
+pub struct CommandQueue {
+    commands: Vec<Command>,
+}
+
+// The commands are only reachable through `&mut self`, so sharing the queue cannot run them from two threads.
+unsafe impl Sync for CommandQueue { }
+
+impl CommandQueue {
+    pub fn new() -> Self {
+        Self {
+            commands: Vec::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    pub fn push(&mut self, command: impl FnOnce(&mut WorldEntitiesComponents) + Send + 'static) {
+        self.commands.push(Box::new(command));
+    }
+
+    /// Reserves the entity right away, so it can be used by the next commands. The bundle is inserted once the commands are applied.
+    pub fn spawn<B: Bundle + Send>(&mut self, world: &WorldEntitiesComponents, bundle: B) -> Entity {
+        let entity = world.reserve_entity();
+
+        self.insert_bundle(entity, bundle);
+
+        entity
+    }
+
+    pub fn insert_bundle<B: Bundle + Send>(&mut self, entity: Entity, bundle: B) {
+        self.push(move |w| { w.insert_bundle(entity, bundle); });
+    }
+
+    pub fn add_component<C: Component + Send>(&mut self, entity: Entity, component: C) {
+        self.push(move |w| { w.add_component(entity, component); });
+    }
+
+    pub fn insert_component<C: Component + Send>(&mut self, entity: Entity, component: C) {
+        self.push(move |w| { w.insert_component(entity, component); });
+    }
+
+    pub fn remove_component<C: Component>(&mut self, entity: Entity) {
+        self.push(move |w| { w.remove_component::<C>(entity); });
+    }
+
+    pub fn destroy_entity(&mut self, entity: Entity) {
+        self.push(move |w| { w.destroy_entity(entity); });
+    }
+
+    /// Applies the commands in the order they were pushed, after creating the reserved entities.
+    pub fn apply(&mut self, world: &mut WorldEntitiesComponents) {
+        world.flush_reserved_entities();
+
+        for command in self.commands.drain(..) {
+            command(world);
+        }
+    }
+}
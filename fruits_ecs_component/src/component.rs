@@ -1,33 +1,124 @@
-use std::{any::TypeId, collections::{HashMap, HashSet}};
+use std::{collections::{HashMap, HashSet}, sync::Arc};
 
 use super::{
     archetype::Archetype,
+    bundle::Bundle,
+    command_queue::CommandQueue,
+    entity::Entity,
+    component_id::{ComponentBitSet, ComponentRegistry},
+    required_components::RequiredComponents,
+    type_info::{CloneFn, ComponentTypeId, TypeInfo},
     unique_components_set::UniqueComponentsSet
 };
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StorageType {
+    /// Stored in archetype chunks. Fast to iterate, but adding or removing moves the whole entity to another archetype.
+    Table,
+    /// Stored in a sparse set keyed by entity. Adding or removing does not change the entity archetype.
+    SparseSet,
+}
+
+/// Hooks get the entity and a queue of commands applied right after the structural change that triggered them.
 pub trait Component: 'static {
-    
+    const STORAGE_TYPE: StorageType = StorageType::Table;
+    /// Used to clone entities. `Some(clone_component::<Self>)` for `Clone` components, `None` if the component cannot be cloned.
+    const CLONE: Option<CloneFn> = None;
+
+    /// Called when the component is added to an entity that did not have it.
+    fn on_add(_entity: Entity, _commands: &mut CommandQueue) { }
+    /// Called after `on_add` and when the component value is overwritten.
+    fn on_insert(_entity: Entity, _commands: &mut CommandQueue) { }
+    /// Called when the component is removed, including the destruction of its entity.
+    fn on_remove(_entity: Entity, _commands: &mut CommandQueue) { }
+
+    /// Registers the components inserted with their default values in the same archetype move when this one is added.
+    fn register_required(_required: &mut RequiredComponents) { }
+}
+
+/// Type erased component hooks.
+#[derive(Clone, Copy)]
+pub struct ComponentHooks {
+    pub on_add: fn(Entity, &mut CommandQueue),
+    pub on_insert: fn(Entity, &mut CommandQueue),
+    pub on_remove: fn(Entity, &mut CommandQueue),
+}
+
+impl ComponentHooks {
+    pub const EMPTY: Self = Self {
+        on_add: |_, _| { },
+        on_insert: |_, _| { },
+        on_remove: |_, _| { },
+    };
+
+    pub fn of<C: Component>() -> Self {
+        Self {
+            on_add: C::on_add,
+            on_insert: C::on_insert,
+            on_remove: C::on_remove,
+        }
+    }
+}
+
+/// Cached archetype transitions keyed by a component or a bundle type. `None` means the transition is not possible.
+#[derive(Default)]
+pub(crate) struct ArchetypeEdges {
+    pub(crate) with_component: HashMap<ComponentTypeId, Option<usize>>,
+    pub(crate) without_component: HashMap<ComponentTypeId, Option<usize>>,
 }
 
 pub struct WorldArchetypes {
-    archetype_id_by_components: HashMap<UniqueComponentsSet, usize>,
-    archetype_ids_by_component: HashMap<TypeId, HashSet<usize>>,
+    components: ComponentRegistry,
+    archetype_id_by_components: HashMap<ComponentBitSet, usize>,
+    /// Component bits of every archetype by id, so matching scans a single contiguous slice.
+    archetype_components: Vec<ComponentBitSet>,
     archetypes: Vec<Archetype>,
+    pub(crate) edges: Vec<ArchetypeEdges>,
+    required_components: HashMap<ComponentTypeId, Arc<RequiredComponents>>,
 }
 
 impl WorldArchetypes {
     pub fn new() -> Self {
         Self {
+            components: ComponentRegistry::new(),
             archetype_id_by_components: HashMap::new(),
-            archetype_ids_by_component: HashMap::new(),
+            archetype_components: Vec::new(),
             archetypes: Vec::new(),
+            edges: Vec::new(),
+            required_components: HashMap::new(),
         }
     }
 
+    pub fn components(&self) -> &ComponentRegistry {
+        &self.components
+    }
+
+    pub fn components_mut(&mut self) -> &mut ComponentRegistry {
+        &mut self.components
+    }
+
+    /// Table component bits of the archetypes, indexed by archetype id.
+    pub fn all_components(&self) -> &[ComponentBitSet] {
+        &self.archetype_components
+    }
+
+    /// Ids of the archetypes with all the `components`.
+    pub fn ids_with_components<'a>(&'a self, components: &'a ComponentBitSet) -> impl Iterator<Item = usize> + 'a {
+        self.archetype_components.iter()
+            .enumerate()
+            .filter(|(_, c)| c.contains_all(components))
+            .map(|(id, _)| id)
+    }
+
     pub fn all(&self) -> &[Archetype] {
         &self.archetypes
     }
 
+    /// Archetypes should keep their layouts, only the entities may change.
+    pub(crate) fn all_mut(&mut self) -> &mut [Archetype] {
+        &mut self.archetypes
+    }
+
     pub fn by_id_ref(&self, id: usize) -> Option<&Archetype> {
         self.archetypes.get(id)
     }
@@ -81,24 +172,26 @@ impl WorldArchetypes {
         self.by_id_mut(id)
     }
     pub fn id_by_components(&self, components: &UniqueComponentsSet) -> Option<usize> {
-        self.archetype_id_by_components.get(&components).copied()
-    }
-    pub fn ids_by_component(&self, component: &TypeId) -> Option<&HashSet<usize>> {
-        self.archetype_ids_by_component.get(component)
+        let bits = self.components.bits_by_types(components.component_infos().keys())?;
+        self.archetype_id_by_components.get(&bits).copied()
     }
     pub fn create(&mut self, components: UniqueComponentsSet) -> Result<usize, UniqueComponentsSet> {
-        if self.archetype_id_by_components.contains_key(&components) {
-            return Err(components);
-        }
+        let mut bits = ComponentBitSet::new();
 
-        let id = self.archetype_id_by_components.len();
+        for info in components.component_infos().values() {
+            bits.insert(self.components.register_info(*info));
+        }
 
-        for &component_type in components.component_infos().keys() {
-            self.archetype_ids_by_component.entry(component_type).or_default().insert(id);
+        if self.archetype_id_by_components.contains_key(&bits) {
+            return Err(components);
         }
 
-        self.archetypes.push(Archetype::new_from_components(components.clone()));
-        self.archetype_id_by_components.insert(components, id);
+        let id = self.archetypes.len();
+
+        self.archetypes.push(Archetype::new_from_components(components));
+        self.archetype_components.push(bits);
+        self.edges.push(ArchetypeEdges::default());
+        self.archetype_id_by_components.insert(bits, id);
 
         Ok(id)
     }
@@ -109,4 +202,98 @@ impl WorldArchetypes {
 
         (id, Some(components))
     }
-}
\ No newline at end of file
+
+    /// Components required by `C`, cached per type.
+    pub fn required_components<C: Component>(&mut self) -> Arc<RequiredComponents> {
+        Arc::clone(self.required_components.entry(ComponentTypeId::of::<C>()).or_insert_with(|| Arc::new(RequiredComponents::of::<C>())))
+    }
+    /// Components required by the bundle components, cached per bundle type.
+    pub fn required_bundle_components<B: Bundle>(&mut self) -> Arc<RequiredComponents> {
+        Arc::clone(self.required_components.entry(ComponentTypeId::of::<B>()).or_insert_with(|| Arc::new(RequiredComponents::of_bundle::<B>())))
+    }
+
+    /// Id of the archetype with the same components plus `C`. `None` if the archetype already has `C`.
+    /// Components required by `C` are not added.
+    pub fn id_with_component<C: Component>(&mut self, id: usize) -> Option<usize> {
+        self.id_with_component_info(id, TypeInfo::of_component::<C>())
+    }
+    pub fn id_with_component_info(&mut self, id: usize, info: TypeInfo) -> Option<usize> {
+        self.id_with_edge(id, *info.id(), true, |components| components.insert_info(info))
+    }
+    /// Id of the archetype with the same components minus `C`. `None` if the archetype does not have `C`.
+    pub fn id_without_component<C: Component>(&mut self, id: usize) -> Option<usize> {
+        self.id_without_component_type(id, ComponentTypeId::of::<C>())
+    }
+    pub fn id_without_component_type(&mut self, id: usize, component_type: ComponentTypeId) -> Option<usize> {
+        self.id_without_edge(id, component_type, true, |components| components.remove_type(&component_type))
+    }
+    /// Id of the archetype with the same components plus the bundle table components and their required table components.
+    /// The archetype should have none of the bundle components.
+    pub fn id_with_bundle<B: Bundle>(&mut self, id: usize) -> usize {
+        let required = self.required_bundle_components::<B>();
+
+        // Removing the bundle keeps the required components, so such a move cannot be reversed by the same edge.
+        self.id_with_edge(id, ComponentTypeId::of::<B>(), !required.has_table_components(), |components| {
+            *components = components.union(&bundle_table_components::<B>());
+            required.fill_table_components(components);
+            true
+        }).unwrap()
+    }
+    /// Id of the archetype with the same components minus the bundle table components.
+    /// The archetype should have all of the bundle components.
+    pub fn id_without_bundle<B: Bundle>(&mut self, id: usize) -> usize {
+        let is_reversible = !self.required_bundle_components::<B>().has_table_components();
+
+        self.id_without_edge(id, ComponentTypeId::of::<B>(), is_reversible, |components| {
+            *components = components.difference(&bundle_table_components::<B>());
+            true
+        }).unwrap()
+    }
+
+    fn id_with_edge(&mut self, id: usize, edge: ComponentTypeId, is_reversible: bool, change: impl FnOnce(&mut UniqueComponentsSet) -> bool) -> Option<usize> {
+        if let Some(&dst_id) = self.edges[id].with_component.get(&edge) {
+            return dst_id;
+        }
+
+        let dst_id = self.id_by_changed_components(id, change);
+
+        self.edges[id].with_component.insert(edge, dst_id);
+
+        if let Some(dst_id) = dst_id.filter(|_| is_reversible) {
+            self.edges[dst_id].without_component.insert(edge, Some(id));
+        }
+
+        dst_id
+    }
+    fn id_without_edge(&mut self, id: usize, edge: ComponentTypeId, is_reversible: bool, change: impl FnOnce(&mut UniqueComponentsSet) -> bool) -> Option<usize> {
+        if let Some(&dst_id) = self.edges[id].without_component.get(&edge) {
+            return dst_id;
+        }
+
+        let dst_id = self.id_by_changed_components(id, change);
+
+        self.edges[id].without_component.insert(edge, dst_id);
+
+        if let Some(dst_id) = dst_id.filter(|_| is_reversible) {
+            self.edges[dst_id].with_component.insert(edge, Some(id));
+        }
+
+        dst_id
+    }
+    fn id_by_changed_components(&mut self, id: usize, change: impl FnOnce(&mut UniqueComponentsSet) -> bool) -> Option<usize> {
+        let mut components = self.archetypes[id].components_set().clone();
+
+        match change(&mut components) {
+            true => Some(self.id_by_components_or_create(components).0),
+            false => None,
+        }
+    }
+}
+
+fn bundle_table_components<B: Bundle>() -> UniqueComponentsSet {
+    let mut table_components = UniqueComponentsSet::new();
+
+    B::fill_components(&mut table_components, &mut HashSet::new());
+
+    table_components
+}
@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+
+use super::{component::Component, type_info::{ComponentTypeId, DynamicComponentDescriptor, TypeInfo}};
+
+const BIT_SET_WORDS_COUNT: usize = 4;
+
+/// Maximum count of component types a single world can register.
+pub const MAX_COMPONENTS_COUNT: usize = BIT_SET_WORDS_COUNT * u64::BITS as usize;
+
+/// Dense world-local component index.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct ComponentId(u32);
+
+impl ComponentId {
+    pub const fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Fixed-width set of component ids.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, Debug)]
+pub struct ComponentBitSet {
+    words: [u64; BIT_SET_WORDS_COUNT],
+}
+
+impl ComponentBitSet {
+    pub const fn new() -> Self {
+        Self {
+            words: [0; BIT_SET_WORDS_COUNT],
+        }
+    }
+
+    pub fn insert(&mut self, id: ComponentId) -> bool {
+        let (word, bit) = Self::position(id);
+        let inserted = self.words[word] & bit == 0;
+
+        self.words[word] |= bit;
+        inserted
+    }
+
+    pub fn remove(&mut self, id: ComponentId) -> bool {
+        let (word, bit) = Self::position(id);
+        let removed = self.words[word] & bit != 0;
+
+        self.words[word] &= !bit;
+        removed
+    }
+
+    pub fn contains(&self, id: ComponentId) -> bool {
+        let (word, bit) = Self::position(id);
+        self.words[word] & bit != 0
+    }
+
+    pub fn contains_all(&self, other: &Self) -> bool {
+        self.words.iter().zip(other.words.iter()).all(|(s, o)| s & o == *o)
+    }
+
+    pub fn contains_any(&self, other: &Self) -> bool {
+        self.words.iter().zip(other.words.iter()).any(|(s, o)| s & o != 0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|w| *w == 0)
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        Self {
+            words: std::array::from_fn(|i| self.words[i] | other.words[i]),
+        }
+    }
+
+    pub fn difference(&self, other: &Self) -> Self {
+        Self {
+            words: std::array::from_fn(|i| self.words[i] & !other.words[i]),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = ComponentId> + '_ {
+        self.words.iter().enumerate().flat_map(|(word_index, &word)| {
+            (0..u64::BITS)
+                .filter(move |bit| word & (1 << bit) != 0)
+                .map(move |bit| ComponentId(word_index as u32 * u64::BITS + bit))
+        })
+    }
+
+    fn position(id: ComponentId) -> (usize, u64) {
+        (id.index() / u64::BITS as usize, 1 << (id.index() % u64::BITS as usize))
+    }
+}
+
+/// Maps component types of a world to dense ids.
+pub struct ComponentRegistry {
+    ids_by_type: HashMap<ComponentTypeId, ComponentId>,
+    infos: Vec<TypeInfo>,
+}
+
+impl ComponentRegistry {
+    pub fn new() -> Self {
+        Self {
+            ids_by_type: HashMap::new(),
+            infos: Vec::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.infos.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.infos.is_empty()
+    }
+
+    pub fn register<C: Component>(&mut self) -> ComponentId {
+        self.register_info(TypeInfo::of_component::<C>())
+    }
+
+    /// Registers a new component type defined at runtime.
+    pub fn register_dynamic(&mut self, descriptor: &DynamicComponentDescriptor) -> ComponentId {
+        self.register_info(TypeInfo::new_dynamic(self.infos.len() as u32, descriptor))
+    }
+
+    pub fn register_info(&mut self, info: TypeInfo) -> ComponentId {
+        if let Some(&id) = self.ids_by_type.get(info.id()) {
+            return id;
+        }
+
+        assert!(self.infos.len() < MAX_COMPONENTS_COUNT, "Too many component types. Max count: {MAX_COMPONENTS_COUNT}.");
+
+        let id = ComponentId(self.infos.len() as u32);
+
+        self.ids_by_type.insert(*info.id(), id);
+        self.infos.push(info);
+
+        id
+    }
+
+    pub fn id<C: Component>(&self) -> Option<ComponentId> {
+        self.id_by_type(&ComponentTypeId::of::<C>())
+    }
+
+    pub fn id_by_type(&self, type_id: &ComponentTypeId) -> Option<ComponentId> {
+        self.ids_by_type.get(type_id).copied()
+    }
+
+    pub fn info(&self, id: ComponentId) -> Option<&TypeInfo> {
+        self.infos.get(id.index())
+    }
+
+    /// `None` if any of the types is not registered yet.
+    pub fn bits_by_types<'a, T: Into<ComponentTypeId> + Copy + 'a>(&self, type_ids: impl IntoIterator<Item = &'a T>) -> Option<ComponentBitSet> {
+        let mut bits = ComponentBitSet::new();
+
+        for &type_id in type_ids {
+            bits.insert(self.id_by_type(&type_id.into())?);
+        }
+
+        Some(bits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bit_set_operations() {
+        let ids = [0, 5, 63, 64, 200].map(ComponentId);
+
+        let mut all = ComponentBitSet::new();
+        let mut some = ComponentBitSet::new();
+
+        for id in ids {
+            assert!(all.insert(id));
+        }
+
+        assert!(!all.insert(ids[0]));
+
+        some.insert(ids[1]);
+        some.insert(ids[3]);
+
+        assert!(all.contains_all(&some));
+        assert!(!some.contains_all(&all));
+        assert_eq!(all.difference(&some).iter().collect::<Vec<_>>(), [ids[0], ids[2], ids[4]]);
+        assert_eq!(all.difference(&some).union(&some), all);
+
+        assert!(all.remove(ids[3]));
+        assert!(!all.contains(ids[3]));
+        assert!(all.contains_any(&some));
+    }
+}
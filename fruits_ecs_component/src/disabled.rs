@@ -0,0 +1,50 @@
+use std::{any::TypeId, collections::HashSet};
+
+use fruits_ecs_data_usage::PerTypeDataUsage;
+
+use super::{
+    archetype::{Archetype, FetchContext},
+    component::Component,
+    query_filter::QueryFilter,
+    type_info::{clone_component, CloneFn},
+};
+
+/// Marks an entity skipped by queries without removing its components.
+/// Queries mentioning `Disabled` or filtered by `IncludeDisabled` still yield the entity.
+#[derive(Clone, Copy, Default)]
+pub struct Disabled;
+
+impl Component for Disabled {
+    const CLONE: Option<CloneFn> = Some(clone_component::<Self>);
+}
+
+/// Passes the entities with and without the `Disabled` marker.
+pub struct IncludeDisabled;
+
+unsafe impl QueryFilter for IncludeDisabled {
+    type Column<'w> = ();
+
+    fn column<'w>(_context: FetchContext<'w>, _chunk_index: usize) -> Self::Column<'w> { }
+
+    unsafe fn matches_row<'w>(_column: Self::Column<'w>, _entity_in_chunk_index: usize) -> bool {
+        true
+    }
+
+    fn fill_usage(_usage: &mut PerTypeDataUsage) { }
+
+    fn fill_archetype_components(_components: &mut HashSet<TypeId>) { }
+
+    fn matches_archetype(_archetype: &Archetype) -> bool {
+        true
+    }
+
+    const IS_DENSE: bool = true;
+
+    fn includes_disabled() -> bool {
+        true
+    }
+}
+
+pub(crate) fn is_disabled_marker<C: Component>() -> bool {
+    TypeId::of::<C>() == TypeId::of::<Disabled>()
+}
@@ -1,11 +1,12 @@
-use std::fmt::Debug;
+use std::{cmp::Ordering, fmt::{Debug, Display}};
 
 use fruits_utils::index_version_collection::{
     VersionCollection,
     VersionIndex,
 };
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+/// 32-bit index and 32-bit generation of an entity slot.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Entity(VersionIndex);
 
 impl Entity {
@@ -14,6 +15,29 @@ impl Entity {
     pub fn version_index(&self) -> VersionIndex {
         self.0
     }
+
+    /// The generation in the high bits and the index in the low bits, stable across runs of the same world.
+    pub const fn to_bits(self) -> u64 {
+        ((self.0.version as u64) << 32) | self.0.index as u64
+    }
+
+    /// Any bits are accepted, the entities not existing in the world are just never found.
+    pub const fn from_bits(bits: u64) -> Self {
+        Self(VersionIndex { index: bits as u32, version: (bits >> 32) as u32 })
+    }
+}
+
+/// Orders by generation and then by index, same as the bits.
+impl Ord for Entity {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.to_bits().cmp(&other.to_bits())
+    }
+}
+
+impl PartialOrd for Entity {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
 }
 
 impl Default for Entity {
@@ -28,12 +52,19 @@ impl Debug for Entity {
     }
 }
 
+impl Display for Entity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}v{}", self.0.index, self.0.version)
+    }
+}
+
 #[derive(Clone, Copy)]
 pub struct EntityLocation {
     pub archetype_id: usize,
     pub entity_archetype_index: usize,
 }
 
+#[derive(Clone)]
 pub struct WorldEntities(VersionCollection<EntityLocation>);
 
 impl WorldEntities {
@@ -45,6 +76,22 @@ impl WorldEntities {
         Entity(self.0.insert(location))
     }
 
+    /// Registers an entity for every location in one pass.
+    pub fn insert_batch(&mut self, locations: impl ExactSizeIterator<Item = EntityLocation>) -> Vec<Entity> {
+        self.0.reserve(locations.len());
+
+        locations.map(|location| self.insert(location)).collect()
+    }
+
+    /// The entity is not contained until the reserved entities are flushed.
+    pub fn reserve_entity(&self) -> Entity {
+        Entity(self.0.reserve_index())
+    }
+
+    pub fn flush_reserved(&mut self, mut location: impl FnMut(Entity) -> EntityLocation) {
+        self.0.flush_reserved(|index| location(Entity(index)));
+    }
+
     pub fn remove(&mut self, entity: Entity) -> Option<EntityLocation> {
         self.0.remove(entity.0)
     }
@@ -64,4 +111,8 @@ impl WorldEntities {
     pub fn len(&self) -> usize {
         self.0.len()
     }
+
+    pub fn reserve(&mut self, additional: usize) {
+        self.0.reserve(additional);
+    }
 }
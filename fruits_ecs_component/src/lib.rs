@@ -1,4 +1,5 @@
 mod component;
+mod component_id;
 mod entity;
 mod world_entities_components;
 mod archetype;
@@ -7,8 +8,20 @@ mod archetype_layout;
 mod unique_components_set;
 mod data_rw_lock;
 mod type_info;
+mod sparse_set;
+mod bundle;
+mod command_queue;
+mod change_detection;
+mod query_filter;
+mod query_par_iter;
+mod query_state;
+mod removed_components;
+mod required_components;
+mod snapshot;
+mod disabled;
 
 pub use component::*;
+pub use component_id::*;
 pub use entity::*;
 pub use world_entities_components::*;
 pub use archetype::*;
@@ -16,4 +29,15 @@ pub use unsafe_archetype::*;
 pub use archetype_layout::*;
 pub use unique_components_set::*;
 pub use data_rw_lock::*;
-pub use type_info::*;
\ No newline at end of file
+pub use type_info::*;
+pub use sparse_set::*;
+pub use bundle::*;
+pub use command_queue::*;
+pub use change_detection::*;
+pub use query_filter::*;
+pub use query_par_iter::*;
+pub use query_state::*;
+pub use removed_components::*;
+pub use required_components::*;
+pub use snapshot::*;
+pub use disabled::*;
\ No newline at end of file
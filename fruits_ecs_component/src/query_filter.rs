@@ -0,0 +1,291 @@
+use std::{any::TypeId, collections::HashSet, marker::PhantomData};
+
+use fruits_ecs_data_usage::PerTypeDataUsage;
+
+use super::{
+    archetype::{Archetype, ArchetypeIteratorItem, ComponentColumn, FetchContext},
+    component::{Component, StorageType},
+    disabled::{is_disabled_marker, Disabled},
+};
+
+/// Restricts the rows a query yields without fetching anything.
+///
+/// # Safety
+/// `fill_usage` should cover every component the filter reads.
+pub unsafe trait QueryFilter: 'static {
+    /// Data of a single archetype chunk the filter checks rows against.
+    type Column<'w>: Copy;
+
+    fn column<'w>(context: FetchContext<'w>, chunk_index: usize) -> Self::Column<'w>;
+    /// # Safety
+    /// `entity_in_chunk_index` should be less than the count of alive entities in the column chunk.
+    unsafe fn matches_row<'w>(column: Self::Column<'w>, entity_in_chunk_index: usize) -> bool;
+    fn fill_usage(usage: &mut PerTypeDataUsage);
+    /// Components every matching archetype should contain.
+    fn fill_archetype_components(components: &mut HashSet<TypeId>);
+    /// Whether any row of the archetype can pass the filter. Columns are only created for matching archetypes.
+    fn matches_archetype(archetype: &Archetype) -> bool;
+    /// Whether every row of a matching archetype passes the filter.
+    const IS_DENSE: bool;
+    /// Whether the query yields the entities with the `Disabled` marker.
+    fn includes_disabled() -> bool {
+        false
+    }
+}
+
+unsafe impl QueryFilter for () {
+    type Column<'w> = ();
+
+    fn column<'w>(_context: FetchContext<'w>, _chunk_index: usize) -> Self::Column<'w> { }
+
+    unsafe fn matches_row<'w>(_column: Self::Column<'w>, _entity_in_chunk_index: usize) -> bool {
+        true
+    }
+
+    fn fill_usage(_usage: &mut PerTypeDataUsage) { }
+
+    fn fill_archetype_components(_components: &mut HashSet<TypeId>) { }
+
+    fn matches_archetype(_archetype: &Archetype) -> bool {
+        true
+    }
+
+    const IS_DENSE: bool = true;
+}
+
+/// All the filters of the tuple should pass.
+macro_rules! query_filter_impl {
+    ($($P: ident),+) => {
+        unsafe impl<$($P),+> QueryFilter for ($($P,)+)
+        where
+            $($P: QueryFilter),+
+        {
+            type Column<'w> = (
+                $($P::Column<'w>,)+
+            );
+
+            fn column<'w>(context: FetchContext<'w>, chunk_index: usize) -> Self::Column<'w> {
+                (
+                    $($P::column(context, chunk_index),)+
+                )
+            }
+
+            #[allow(non_snake_case)]
+            unsafe fn matches_row<'w>(column: Self::Column<'w>, entity_in_chunk_index: usize) -> bool {
+                let ($($P,)+) = column;
+
+                $($P::matches_row($P, entity_in_chunk_index))&&+
+            }
+
+            fn fill_usage(usage: &mut PerTypeDataUsage) {
+                $($P::fill_usage(usage));+;
+            }
+
+            fn fill_archetype_components(components: &mut HashSet<TypeId>) {
+                $($P::fill_archetype_components(components));+;
+            }
+
+            fn matches_archetype(archetype: &Archetype) -> bool {
+                $($P::matches_archetype(archetype))&&+
+            }
+
+            const IS_DENSE: bool = $($P::IS_DENSE)&&+;
+
+            fn includes_disabled() -> bool {
+                $($P::includes_disabled())||+
+            }
+        }
+    };
+}
+
+query_filter_impl!(P0);
+query_filter_impl!(P0, P1);
+query_filter_impl!(P0, P1, P2);
+query_filter_impl!(P0, P1, P2, P3);
+query_filter_impl!(P0, P1, P2, P3, P4);
+query_filter_impl!(P0, P1, P2, P3, P4, P5);
+query_filter_impl!(P0, P1, P2, P3, P4, P5, P6);
+query_filter_impl!(P0, P1, P2, P3, P4, P5, P6, P7);
+query_filter_impl!(P0, P1, P2, P3, P4, P5, P6, P7, P8);
+query_filter_impl!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9);
+query_filter_impl!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9, P10);
+query_filter_impl!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9, P10, P11);
+query_filter_impl!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9, P10, P11, P12);
+query_filter_impl!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9, P10, P11, P12, P13);
+query_filter_impl!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9, P10, P11, P12, P13, P14);
+
+/// Passes the entities with the `C` component. Takes no data locks.
+pub struct With<C: Component>(PhantomData<C>);
+
+/// Passes the entities without the `C` component. Takes no data locks.
+pub struct Without<C: Component>(PhantomData<C>);
+
+/// Passes the entities passing any of the tuple filters.
+pub struct Or<F>(PhantomData<F>);
+
+/// Sparse set membership is only known per row, table membership is known per archetype.
+fn sparse_set_column<'w, C: Component>(context: FetchContext<'w>, chunk_index: usize) -> Option<ComponentColumn<'w, C>> {
+    match C::STORAGE_TYPE {
+        StorageType::Table => None,
+        StorageType::SparseSet => Some(ComponentColumn::new(context, chunk_index)),
+    }
+}
+
+unsafe impl<C: Component> QueryFilter for With<C> {
+    type Column<'w> = Option<ComponentColumn<'w, C>>;
+
+    fn column<'w>(context: FetchContext<'w>, chunk_index: usize) -> Self::Column<'w> {
+        sparse_set_column(context, chunk_index)
+    }
+
+    unsafe fn matches_row<'w>(column: Self::Column<'w>, entity_in_chunk_index: usize) -> bool {
+        column.is_none_or(|c| c.contains(entity_in_chunk_index))
+    }
+
+    fn fill_usage(_usage: &mut PerTypeDataUsage) { }
+
+    fn fill_archetype_components(components: &mut HashSet<TypeId>) {
+        ComponentColumn::<C>::fill_archetype_components(components);
+    }
+
+    fn matches_archetype(archetype: &Archetype) -> bool {
+        ComponentColumn::<C>::matches_archetype(archetype)
+    }
+
+    const IS_DENSE: bool = matches!(C::STORAGE_TYPE, StorageType::Table);
+
+    fn includes_disabled() -> bool {
+        is_disabled_marker::<C>()
+    }
+}
+
+unsafe impl<C: Component> QueryFilter for Without<C> {
+    type Column<'w> = Option<ComponentColumn<'w, C>>;
+
+    fn column<'w>(context: FetchContext<'w>, chunk_index: usize) -> Self::Column<'w> {
+        sparse_set_column(context, chunk_index)
+    }
+
+    unsafe fn matches_row<'w>(column: Self::Column<'w>, entity_in_chunk_index: usize) -> bool {
+        column.is_none_or(|c| !c.contains(entity_in_chunk_index))
+    }
+
+    fn fill_usage(_usage: &mut PerTypeDataUsage) { }
+
+    fn fill_archetype_components(_components: &mut HashSet<TypeId>) { }
+
+    fn matches_archetype(archetype: &Archetype) -> bool {
+        C::STORAGE_TYPE == StorageType::SparseSet || !archetype.contains_component_type::<C>()
+    }
+
+    const IS_DENSE: bool = matches!(C::STORAGE_TYPE, StorageType::Table);
+}
+
+macro_rules! or_query_filter_impl {
+    ($($P: ident),+) => {
+        unsafe impl<$($P),+> QueryFilter for Or<($($P,)+)>
+        where
+            $($P: QueryFilter),+
+        {
+            /// `None` for the filters the archetype does not match.
+            type Column<'w> = (
+                $(Option<$P::Column<'w>>,)+
+            );
+
+            fn column<'w>(context: FetchContext<'w>, chunk_index: usize) -> Self::Column<'w> {
+                (
+                    $($P::matches_archetype(context.archetype).then(|| $P::column(context, chunk_index)),)+
+                )
+            }
+
+            #[allow(non_snake_case)]
+            unsafe fn matches_row<'w>(column: Self::Column<'w>, entity_in_chunk_index: usize) -> bool {
+                let ($($P,)+) = column;
+
+                $($P.is_some_and(|c| $P::matches_row(c, entity_in_chunk_index)))||+
+            }
+
+            fn fill_usage(usage: &mut PerTypeDataUsage) {
+                $($P::fill_usage(usage));+;
+            }
+
+            fn fill_archetype_components(_components: &mut HashSet<TypeId>) { }
+
+            fn matches_archetype(archetype: &Archetype) -> bool {
+                $($P::matches_archetype(archetype))||+
+            }
+
+            /// A matching archetype matches one of the filters, which passes all the rows if dense.
+            const IS_DENSE: bool = $($P::IS_DENSE)&&+;
+
+            fn includes_disabled() -> bool {
+                $($P::includes_disabled())||+
+            }
+        }
+    };
+}
+
+or_query_filter_impl!(P0);
+or_query_filter_impl!(P0, P1);
+or_query_filter_impl!(P0, P1, P2);
+or_query_filter_impl!(P0, P1, P2, P3);
+or_query_filter_impl!(P0, P1, P2, P3, P4);
+or_query_filter_impl!(P0, P1, P2, P3, P4, P5);
+or_query_filter_impl!(P0, P1, P2, P3, P4, P5, P6);
+or_query_filter_impl!(P0, P1, P2, P3, P4, P5, P6, P7);
+or_query_filter_impl!(P0, P1, P2, P3, P4, P5, P6, P7, P8);
+or_query_filter_impl!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9);
+or_query_filter_impl!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9, P10);
+or_query_filter_impl!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9, P10, P11);
+or_query_filter_impl!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9, P10, P11, P12);
+or_query_filter_impl!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9, P10, P11, P12, P13);
+or_query_filter_impl!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9, P10, P11, P12, P13, P14);
+
+/// Fetches `A` from the rows passing `F`.
+pub struct Filtered<A: ArchetypeIteratorItem, F: QueryFilter>(PhantomData<(A, F)>);
+
+unsafe impl<A: ArchetypeIteratorItem, F: QueryFilter> ArchetypeIteratorItem for Filtered<A, F> {
+    type Item<'w> = A::Item<'w>;
+    type ReadOnlyItem<'w> = A::ReadOnlyItem<'w>;
+    type Column<'w> = (A::Column<'w>, F::Column<'w>);
+    type Slice<'w> = A::Slice<'w>;
+
+    fn column<'w>(context: FetchContext<'w>, chunk_index: usize) -> Self::Column<'w> {
+        (A::column(context, chunk_index), F::column(context, chunk_index))
+    }
+
+    unsafe fn matches_row<'w>((item, filter): Self::Column<'w>, entity_in_chunk_index: usize) -> bool {
+        F::matches_row(filter, entity_in_chunk_index) && A::matches_row(item, entity_in_chunk_index)
+    }
+
+    unsafe fn from_column<'w>((item, _): Self::Column<'w>, entity_in_chunk_index: usize) -> Self::Item<'w> {
+        A::from_column(item, entity_in_chunk_index)
+    }
+
+    unsafe fn slice_from_column<'w>((item, _): Self::Column<'w>, entities_count: usize) -> Self::Slice<'w> {
+        A::slice_from_column(item, entities_count)
+    }
+
+    fn fill_usage(usage: &mut PerTypeDataUsage) {
+        A::fill_usage(usage);
+        F::fill_usage(usage);
+    }
+
+    fn fill_archetype_components(components: &mut HashSet<TypeId>) {
+        A::fill_archetype_components(components);
+        F::fill_archetype_components(components);
+    }
+
+    /// Disabled entities are skipped unless `A` or `F` includes them.
+    fn matches_archetype(archetype: &Archetype) -> bool {
+        A::matches_archetype(archetype)
+            && F::matches_archetype(archetype)
+            && (Self::includes_disabled() || !archetype.contains_component_type::<Disabled>())
+    }
+
+    const IS_DENSE: bool = A::IS_DENSE && F::IS_DENSE;
+
+    fn includes_disabled() -> bool {
+        A::includes_disabled() || F::includes_disabled()
+    }
+}
@@ -0,0 +1,91 @@
+use std::{marker::PhantomData, ops::Range};
+
+use fruits_utils::thread_pool::ThreadPool;
+
+use super::{
+    archetype::ArchetypeIteratorItem, change_detection::RunTicks, component::WorldArchetypes,
+    query_filter::{Filtered, QueryFilter}, sparse_set::WorldSparseSets,
+};
+
+/// Runs a function for every query item on a thread pool.
+/// The work is split by archetype chunks, chunks larger than the batch size are split further.
+pub struct QueryParIter<'r, 'w, A: ArchetypeIteratorItem, F: QueryFilter> {
+    archetype_indices: &'r [usize],
+    archetypes: &'w WorldArchetypes,
+    sparse_sets: &'w WorldSparseSets,
+    ticks: RunTicks,
+    thread_pool: &'r ThreadPool,
+    batch_size: Option<usize>,
+    _phantom: PhantomData<fn(A) -> A>,
+    _filter: PhantomData<fn() -> F>,
+}
+
+impl<'r, 'w, A: ArchetypeIteratorItem + 'static, F: QueryFilter> QueryParIter<'r, 'w, A, F> {
+    pub(crate) fn new(
+        archetype_indices: &'r [usize],
+        archetypes: &'w WorldArchetypes,
+        sparse_sets: &'w WorldSparseSets,
+        ticks: RunTicks,
+        thread_pool: &'r ThreadPool,
+    ) -> Self {
+        Self {
+            archetype_indices,
+            archetypes,
+            sparse_sets,
+            ticks,
+            thread_pool,
+            batch_size: None,
+            _phantom: Default::default(),
+            _filter: Default::default(),
+        }
+    }
+
+    /// Maximum count of entities in a single batch. Defaults to a whole chunk.
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        assert!(batch_size > 0, "Batch size should be positive.");
+
+        self.batch_size = Some(batch_size);
+        self
+    }
+
+    /// Returns once every batch has finished.
+    pub fn for_each(self, f: impl Fn(A::Item<'w>) + Sync)
+        where A::Item<'w>: Send
+    {
+        let batches = self.batches();
+
+        let (archetypes, sparse_sets, ticks) = (self.archetypes, self.sparse_sets, self.ticks);
+
+        self.thread_pool.execute_batches(batches.len(), |batch_index| {
+            let (archetype_id, range) = batches[batch_index].clone();
+
+            let archetype = archetypes.by_id_ref(archetype_id).unwrap();
+
+            for item in archetype.iter_range::<Filtered<A, F>>(sparse_sets, ticks, range) {
+                f(item);
+            }
+        });
+    }
+
+    fn batches(&self) -> Vec<(usize, Range<usize>)> {
+        let mut batches = Vec::new();
+
+        for &archetype_id in self.archetype_indices {
+            let archetype = self.archetypes.by_id_ref(archetype_id).unwrap();
+
+            let entities_count = archetype.entities_count();
+            let entities_per_chunk_count = archetype.layout().entities_per_chunk_count();
+            let batch_size = self.batch_size.map_or(entities_per_chunk_count, |s| s.min(entities_per_chunk_count));
+
+            for chunk_start in (0..entities_count).step_by(entities_per_chunk_count) {
+                let chunk_end = (chunk_start + entities_per_chunk_count).min(entities_count);
+
+                for batch_start in (chunk_start..chunk_end).step_by(batch_size) {
+                    batches.push((archetype_id, batch_start..(batch_start + batch_size).min(chunk_end)));
+                }
+            }
+        }
+
+        batches
+    }
+}
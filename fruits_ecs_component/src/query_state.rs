@@ -0,0 +1,67 @@
+use std::{any::TypeId, collections::HashSet, marker::PhantomData};
+
+use super::{
+    archetype::ArchetypeIteratorItem, component::WorldArchetypes, component_id::ComponentBitSet,
+    query_filter::{Filtered, QueryFilter},
+};
+
+/// Archetypes matched by a query, kept between queries so only the archetypes created since then are checked.
+/// Archetypes are never removed from a world, so the matched ids stay valid.
+pub struct QueryState<A: ArchetypeIteratorItem, F: QueryFilter = ()> {
+    archetype_indices: Vec<usize>,
+    archetypes_count: usize,
+    components: HashSet<TypeId>,
+    /// Resolved once all the required components are registered.
+    component_bits: Option<ComponentBitSet>,
+    _phantom: PhantomData<fn(A) -> A>,
+    _filter: PhantomData<fn() -> F>,
+}
+
+impl<A: ArchetypeIteratorItem, F: QueryFilter> QueryState<A, F> {
+    pub fn new() -> Self {
+        let mut components = HashSet::new();
+
+        Filtered::<A, F>::fill_archetype_components(&mut components);
+
+        Self {
+            archetype_indices: Vec::new(),
+            archetypes_count: 0,
+            components,
+            component_bits: None,
+            _phantom: Default::default(),
+            _filter: Default::default(),
+        }
+    }
+
+    pub fn archetype_indices(&self) -> &[usize] {
+        &self.archetype_indices
+    }
+
+    /// Checks the archetypes created since the last update.
+    pub(crate) fn update(&mut self, archetypes: &WorldArchetypes) {
+        let archetypes_count = archetypes.all().len();
+
+        if self.component_bits.is_none() {
+            self.component_bits = archetypes.components().bits_by_types(&self.components);
+        }
+
+        // Archetypes can only contain registered components, so nothing matches until all of them are.
+        if let Some(component_bits) = &self.component_bits {
+            let new_archetypes = archetypes.all_components()[self.archetypes_count..].iter().zip(self.archetypes_count..);
+
+            for (archetype_components, id) in new_archetypes {
+                if archetype_components.contains_all(component_bits) && Filtered::<A, F>::matches_archetype(archetypes.by_id_ref(id).unwrap()) {
+                    self.archetype_indices.push(id);
+                }
+            }
+        }
+
+        self.archetypes_count = archetypes_count;
+    }
+}
+
+impl<A: ArchetypeIteratorItem, F: QueryFilter> Default for QueryState<A, F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
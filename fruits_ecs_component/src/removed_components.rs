@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+
+use super::{
+    change_detection::{RunTicks, Tick},
+    entity::Entity,
+    type_info::ComponentTypeId,
+};
+
+/// Entities that lost a component, per component type, with the tick of the removal.
+#[derive(Clone)]
+pub struct RemovedComponentsLog {
+    removed: HashMap<ComponentTypeId, Vec<(Entity, Tick)>>,
+}
+
+impl RemovedComponentsLog {
+    pub fn new() -> Self {
+        Self {
+            removed: HashMap::new(),
+        }
+    }
+
+    pub fn push(&mut self, component_type: ComponentTypeId, entity: Entity, tick: Tick) {
+        self.removed.entry(component_type).or_default().push((entity, tick));
+    }
+
+    /// Entities that lost the component between the run ticks.
+    pub fn iter(&self, component_type: &ComponentTypeId, ticks: RunTicks) -> impl Iterator<Item = Entity> + '_ {
+        self.removed.get(component_type)
+            .into_iter()
+            .flatten()
+            .filter(move |(_, tick)| tick.is_newer_than(ticks.last_run, ticks.this_run))
+            .map(|(entity, _)| *entity)
+    }
+
+    /// Clamps the removal ticks with `Tick::check_tick`.
+    pub fn check_change_ticks(&mut self, tick: Tick) {
+        for (_, removal_tick) in self.removed.values_mut().flatten() {
+            removal_tick.check_tick(tick);
+        }
+    }
+
+    /// Forgets the removals made before `tick`.
+    pub fn clear_before(&mut self, tick: Tick) {
+        for removed in self.removed.values_mut() {
+            removed.retain(|(_, removal_tick)| !removal_tick.is_older_than(tick));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{component::{Component, StorageType}, world_entities_components::WorldEntitiesComponents};
+
+    use super::*;
+
+    struct Position(#[allow(dead_code)] u32);
+    impl Component for Position { }
+
+    struct Tag;
+    impl Component for Tag {
+        const STORAGE_TYPE: StorageType = StorageType::SparseSet;
+    }
+
+    #[test]
+    fn removals_are_visible_to_the_next_run_until_cleared() {
+        let mut entities_components = WorldEntitiesComponents::new();
+
+        let entities = entities_components.spawn_batch((0..4).map(|i| (Position(i), Tag)));
+
+        let first_run = RunTicks { last_run: Tick::default(), this_run: entities_components.increment_change_tick() };
+
+        entities_components.remove_component::<Position>(entities[0]);
+        entities_components.remove_bundle::<(Tag,)>(entities[1]);
+        entities_components.destroy_entity(entities[2]);
+
+        assert_eq!(entities_components.removed_components::<Position>(first_run).count(), 0);
+
+        let second_run = RunTicks { last_run: first_run.this_run, this_run: entities_components.increment_change_tick() };
+
+        assert_eq!(entities_components.removed_components::<Position>(second_run).collect::<Vec<_>>(), [entities[0], entities[2]]);
+        assert_eq!(entities_components.removed_components::<Tag>(second_run).collect::<Vec<_>>(), [entities[1], entities[2]]);
+
+        let third_run = RunTicks { last_run: second_run.this_run, this_run: entities_components.increment_change_tick() };
+
+        assert_eq!(entities_components.removed_components::<Position>(third_run).count(), 0);
+
+        entities_components.clear_removed_components(third_run.this_run);
+
+        assert_eq!(entities_components.removed_components::<Tag>(second_run).count(), 0);
+    }
+}
@@ -0,0 +1,140 @@
+use super::{
+    archetype::Archetype,
+    bundle::Bundle,
+    change_detection::Tick,
+    command_queue::CommandQueue,
+    component::{Component, StorageType},
+    entity::Entity,
+    sparse_set::WorldSparseSets,
+    type_info::{ComponentTypeId, TypeInfo},
+    unique_components_set::UniqueComponentsSet,
+};
+
+#[derive(Clone, Copy)]
+struct RequiredComponent {
+    info: TypeInfo,
+    storage_type: StorageType,
+    /// Writes the default value, returns whether the entity did not have the component.
+    write_default: unsafe fn(src_archetype: &Archetype, dst_archetype: &Archetype, entity_index: usize, entity: Entity, sparse_sets: &mut WorldSparseSets, tick: Tick) -> bool,
+}
+
+/// Components inserted with their default values together with the components requiring them.
+#[derive(Default)]
+pub struct RequiredComponents {
+    components: Vec<RequiredComponent>,
+}
+
+impl RequiredComponents {
+    pub fn new() -> Self {
+        Self {
+            components: Vec::new(),
+        }
+    }
+
+    /// Requirements of `C` and of all the required components, without `C` itself.
+    pub fn of<C: Component>() -> Self {
+        let mut required = Self::new();
+
+        C::register_required(&mut required);
+
+        required.components.retain(|c| *c.info.id() != ComponentTypeId::of::<C>());
+        required
+    }
+
+    /// Requirements of the bundle components, without the bundle components themselves.
+    pub fn of_bundle<B: Bundle>() -> Self {
+        let mut required = Self::new();
+
+        B::register_required(&mut required);
+
+        let mut table_components = UniqueComponentsSet::new();
+        let mut sparse_components = std::collections::HashSet::new();
+
+        B::fill_components(&mut table_components, &mut sparse_components);
+
+        required.components.retain(|c| {
+            !table_components.component_infos().contains_key(c.info.id()) && !matches!(c.info.id(), ComponentTypeId::Type(type_id) if sparse_components.contains(type_id))
+        });
+        required
+    }
+
+    pub fn len(&self) -> usize {
+        self.components.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.components.is_empty()
+    }
+
+    /// Requires `R` and everything `R` requires.
+    pub fn require<R: Component + Default>(&mut self) {
+        if self.components.iter().any(|c| *c.info.id() == ComponentTypeId::of::<R>()) {
+            return;
+        }
+
+        self.components.push(RequiredComponent {
+            info: TypeInfo::of_component::<R>(),
+            storage_type: R::STORAGE_TYPE,
+            write_default: write_default::<R>,
+        });
+
+        R::register_required(self);
+    }
+
+    /// Whether adding the requirements may change the archetype.
+    pub(crate) fn has_table_components(&self) -> bool {
+        self.components.iter().any(|c| c.storage_type == StorageType::Table)
+    }
+
+    /// Adds the required table components to the archetype components.
+    pub(crate) fn fill_table_components(&self, components: &mut UniqueComponentsSet) {
+        for component in self.components.iter().filter(|c| c.storage_type == StorageType::Table) {
+            components.insert_info(component.info);
+        }
+    }
+
+    /// Writes the components the entity had not had in `src_archetype` and runs their add hooks.
+    ///
+    /// # Safety
+    /// The entity row should be alive in `dst_archetype`, which should contain all the required table components.
+    /// Required table components missing in `src_archetype` should be uninitialized in the row.
+    pub(crate) unsafe fn write_missing(
+        &self,
+        (src_archetype, dst_archetype): (&Archetype, &Archetype),
+        entity_index: usize,
+        entity: Entity,
+        sparse_sets: &mut WorldSparseSets,
+        tick: Tick,
+        commands: &mut CommandQueue,
+    ) {
+        for component in self.components.iter() {
+            if (component.write_default)(src_archetype, dst_archetype, entity_index, entity, sparse_sets, tick) {
+                (component.info.hooks().on_add)(entity, commands);
+                (component.info.hooks().on_insert)(entity, commands);
+            }
+        }
+    }
+}
+
+unsafe fn write_default<C: Component + Default>(src_archetype: &Archetype, dst_archetype: &Archetype, entity_index: usize, entity: Entity, sparse_sets: &mut WorldSparseSets, tick: Tick) -> bool {
+    match C::STORAGE_TYPE {
+        StorageType::Table => {
+            if src_archetype.contains_component_type::<C>() {
+                return false;
+            }
+
+            dst_archetype.write_component(entity_index, C::default(), tick);
+            true
+        },
+        StorageType::SparseSet => {
+            let sparse_set = sparse_sets.get_or_create_mut::<C>();
+
+            if sparse_set.contains(entity) {
+                return false;
+            }
+
+            sparse_set.insert(entity, C::default(), tick);
+            true
+        },
+    }
+}
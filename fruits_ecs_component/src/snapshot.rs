@@ -0,0 +1,22 @@
+use super::{archetype::Archetype, entity::WorldEntities, sparse_set::WorldSparseSets};
+
+/// Entities and components of a world, restored by `WorldEntitiesComponents::restore`.
+pub struct EntitiesComponentsSnapshot {
+    pub(crate) archetypes: Vec<Archetype>,
+    pub(crate) entities: WorldEntities,
+    pub(crate) sparse_sets: WorldSparseSets,
+}
+
+impl EntitiesComponentsSnapshot {
+    pub fn entities_count(&self) -> usize {
+        self.entities.len()
+    }
+}
+
+impl Drop for EntitiesComponentsSnapshot {
+    fn drop(&mut self) {
+        for archetype in self.archetypes.iter_mut() {
+            archetype.clear();
+        }
+    }
+}
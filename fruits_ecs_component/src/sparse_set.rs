@@ -0,0 +1,319 @@
+use std::{
+    any::{Any, TypeId},
+    cell::UnsafeCell,
+    collections::HashMap,
+    mem::MaybeUninit,
+};
+
+use super::{change_detection::{ComponentTicks, Tick}, component::{Component, ComponentHooks}, entity::Entity, type_info::{ComponentTypeId, TypeInfo}};
+
+pub struct ComponentSparseSet<C: Component> {
+    dense: Vec<UnsafeCell<C>>,
+    dense_ticks: Vec<UnsafeCell<ComponentTicks>>,
+    dense_entities: Vec<Entity>,
+    dense_index_by_entity_index: Vec<Option<usize>>,
+}
+
+// todo: temp
+unsafe impl<C: Component> Send for ComponentSparseSet<C> { }
+// todo: temp
+unsafe impl<C: Component> Sync for ComponentSparseSet<C> { }
+
+impl<C: Component> ComponentSparseSet<C> {
+    pub fn new() -> Self {
+        Self {
+            dense: Vec::new(),
+            dense_ticks: Vec::new(),
+            dense_entities: Vec::new(),
+            dense_index_by_entity_index: Vec::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.dense.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dense.is_empty()
+    }
+
+    pub fn entities(&self) -> &[Entity] {
+        &self.dense_entities
+    }
+
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.dense_index(entity).is_some()
+    }
+
+    /// Returns the component back if the entity already has one.
+    pub fn insert(&mut self, entity: Entity, component: C, tick: Tick) -> Option<C> {
+        if self.contains(entity) {
+            return Some(component);
+        }
+
+        let entity_index = entity.version_index().index as usize;
+
+        if entity_index >= self.dense_index_by_entity_index.len() {
+            self.dense_index_by_entity_index.resize(entity_index + 1, None);
+        }
+
+        self.dense_index_by_entity_index[entity_index] = Some(self.dense.len());
+        self.dense.push(UnsafeCell::new(component));
+        self.dense_ticks.push(UnsafeCell::new(ComponentTicks::new(tick)));
+        self.dense_entities.push(entity);
+
+        None
+    }
+
+    pub fn remove(&mut self, entity: Entity) -> Option<C> {
+        let dense_index = self.dense_index(entity)?;
+
+        self.dense_index_by_entity_index[entity.version_index().index as usize] = None;
+
+        let component = self.dense.swap_remove(dense_index).into_inner();
+        self.dense_ticks.swap_remove(dense_index);
+        self.dense_entities.swap_remove(dense_index);
+
+        if let Some(moved_entity) = self.dense_entities.get(dense_index) {
+            self.dense_index_by_entity_index[moved_entity.version_index().index as usize] = Some(dense_index);
+        }
+
+        Some(component)
+    }
+
+    pub fn get(&self, entity: Entity) -> Option<&C> {
+        let dense_index = self.dense_index(entity)?;
+
+        Some(unsafe { &*self.dense[dense_index].get() })
+    }
+
+    pub fn get_mut(&mut self, entity: Entity) -> Option<&mut C> {
+        let dense_index = self.dense_index(entity)?;
+
+        Some(self.dense[dense_index].get_mut())
+    }
+
+    /// Writing through the pointer requires the same exclusive access as mutating the component.
+    pub fn get_ptr(&self, entity: Entity) -> Option<*mut C> {
+        let dense_index = self.dense_index(entity)?;
+
+        Some(self.dense[dense_index].get())
+    }
+
+    pub fn get_ticks(&self, entity: Entity) -> Option<ComponentTicks> {
+        let dense_index = self.dense_index(entity)?;
+
+        Some(unsafe { *self.dense_ticks[dense_index].get() })
+    }
+
+    /// Writing through the pointer requires the same exclusive access as mutating the component.
+    pub fn get_ticks_ptr(&self, entity: Entity) -> Option<*mut ComponentTicks> {
+        let dense_index = self.dense_index(entity)?;
+
+        Some(self.dense_ticks[dense_index].get())
+    }
+
+    /// Clamps the component ticks with `Tick::check_tick`.
+    pub fn check_change_ticks(&mut self, tick: Tick) {
+        for ticks in self.dense_ticks.iter_mut() {
+            ticks.get_mut().check_ticks(tick);
+        }
+    }
+
+    /// Passes the ticks of every component to `f`.
+    pub fn for_each_ticks_mut(&mut self, mut f: impl FnMut(Entity, &mut ComponentTicks)) {
+        for (entity, ticks) in self.dense_entities.iter().zip(self.dense_ticks.iter_mut()) {
+            f(*entity, ticks.get_mut());
+        }
+    }
+
+    fn dense_index(&self, entity: Entity) -> Option<usize> {
+        let dense_index = (*self.dense_index_by_entity_index.get(entity.version_index().index as usize)?)?;
+
+        if self.dense_entities[dense_index] != entity {
+            return None;
+        }
+
+        Some(dense_index)
+    }
+}
+
+trait AnyComponentSparseSet: Send + Sync {
+    fn remove_entity(&mut self, entity: Entity) -> bool;
+    fn contains_entity(&self, entity: Entity) -> bool;
+    /// Returns `false` if the `src` entity has no component or it cannot be cloned.
+    fn clone_entity(&mut self, src: Entity, dst: Entity, tick: Tick) -> bool;
+    /// `None` if the set has components and they cannot be cloned.
+    fn try_clone(&self) -> Option<Box<dyn AnyComponentSparseSet>>;
+    fn check_change_ticks(&mut self, tick: Tick);
+    fn for_each_ticks_mut(&mut self, f: &mut dyn FnMut(Entity, &mut ComponentTicks));
+    fn entities(&self) -> &[Entity];
+    fn info(&self) -> TypeInfo;
+    fn hooks(&self) -> ComponentHooks;
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<C: Component> AnyComponentSparseSet for ComponentSparseSet<C> {
+    fn remove_entity(&mut self, entity: Entity) -> bool {
+        self.remove(entity).is_some()
+    }
+
+    fn contains_entity(&self, entity: Entity) -> bool {
+        self.contains(entity)
+    }
+
+    fn clone_entity(&mut self, src: Entity, dst: Entity, tick: Tick) -> bool {
+        let (Some(cloner), Some(component)) = (C::CLONE, self.get(src)) else {
+            return false;
+        };
+
+        let mut clone = MaybeUninit::<C>::uninit();
+
+        unsafe { cloner(component as *const C as *const (), clone.as_mut_ptr() as *mut ()) };
+
+        self.insert(dst, unsafe { clone.assume_init() }, tick);
+
+        true
+    }
+
+    fn try_clone(&self) -> Option<Box<dyn AnyComponentSparseSet>> {
+        if self.is_empty() {
+            return Some(Box::new(Self::new()));
+        }
+
+        let cloner = C::CLONE?;
+
+        let dense = self.dense.iter().map(|component| {
+            let mut clone = MaybeUninit::<C>::uninit();
+
+            unsafe { cloner(component.get() as *const (), clone.as_mut_ptr() as *mut ()) };
+
+            UnsafeCell::new(unsafe { clone.assume_init() })
+        }).collect();
+
+        Some(Box::new(Self {
+            dense,
+            dense_ticks: self.dense_ticks.iter().map(|t| UnsafeCell::new(unsafe { *t.get() })).collect(),
+            dense_entities: self.dense_entities.clone(),
+            dense_index_by_entity_index: self.dense_index_by_entity_index.clone(),
+        }))
+    }
+
+    fn check_change_ticks(&mut self, tick: Tick) {
+        self.check_change_ticks(tick);
+    }
+
+    fn for_each_ticks_mut(&mut self, f: &mut dyn FnMut(Entity, &mut ComponentTicks)) {
+        self.for_each_ticks_mut(f);
+    }
+
+    fn entities(&self) -> &[Entity] {
+        self.entities()
+    }
+
+    fn info(&self) -> TypeInfo {
+        TypeInfo::of_component::<C>()
+    }
+
+    fn hooks(&self) -> ComponentHooks {
+        ComponentHooks::of::<C>()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+pub struct WorldSparseSets {
+    sets: HashMap<TypeId, Box<dyn AnyComponentSparseSet>>,
+}
+
+impl WorldSparseSets {
+    pub fn new() -> Self {
+        Self {
+            sets: HashMap::new(),
+        }
+    }
+
+    pub fn get<C: Component>(&self) -> Option<&ComponentSparseSet<C>> {
+        self.sets.get(&TypeId::of::<C>()).map(|s| s.as_any().downcast_ref().unwrap())
+    }
+
+    pub fn get_mut<C: Component>(&mut self) -> Option<&mut ComponentSparseSet<C>> {
+        self.sets.get_mut(&TypeId::of::<C>()).map(|s| s.as_any_mut().downcast_mut().unwrap())
+    }
+
+    pub fn get_or_create_mut<C: Component>(&mut self) -> &mut ComponentSparseSet<C> {
+        self.sets
+            .entry(TypeId::of::<C>())
+            .or_insert_with(|| Box::new(ComponentSparseSet::<C>::new()))
+            .as_any_mut()
+            .downcast_mut()
+            .unwrap()
+    }
+
+    /// Infos of the sparse set components the entity has.
+    pub fn entity_infos(&self, entity: Entity) -> impl Iterator<Item = TypeInfo> + '_ {
+        self.sets.values().filter(move |s| s.contains_entity(entity)).map(|s| s.info())
+    }
+
+    /// Inserts clones of the `src` entity components to the `dst` entity. Components that cannot be cloned are skipped.
+    pub fn clone_entity(&mut self, src: Entity, dst: Entity, tick: Tick) {
+        for set in self.sets.values_mut() {
+            set.clone_entity(src, dst, tick);
+        }
+    }
+
+    /// Infos of the non-empty sets with components that cannot be cloned.
+    pub fn uncloneable_infos(&self) -> impl Iterator<Item = TypeInfo> + '_ {
+        self.sets.values().filter(|s| s.info().cloner().is_none() && s.try_clone().is_none()).map(|s| s.info())
+    }
+
+    /// `None` if any of the components cannot be cloned.
+    pub fn try_clone(&self) -> Option<Self> {
+        let sets = self.sets.iter()
+            .map(|(&component_type, set)| Some((component_type, set.try_clone()?)))
+            .collect::<Option<_>>()?;
+
+        Some(Self { sets })
+    }
+
+    /// Clamps the component ticks of every set with `Tick::check_tick`.
+    pub fn check_change_ticks(&mut self, tick: Tick) {
+        for set in self.sets.values_mut() {
+            set.check_change_ticks(tick);
+        }
+    }
+
+    /// Pairs of every entity and the type of its sparse set component.
+    pub fn entity_component_types(&self) -> impl Iterator<Item = (Entity, ComponentTypeId)> + '_ {
+        self.sets.values().flat_map(|s| {
+            let component_type = *s.info().id();
+
+            s.entities().iter().map(move |entity| (*entity, component_type))
+        })
+    }
+
+    /// Passes the ticks of every sparse set component to `f`.
+    pub fn for_each_ticks_mut(&mut self, mut f: impl FnMut(Entity, &ComponentTypeId, &mut ComponentTicks)) {
+        for set in self.sets.values_mut() {
+            let component_type = *set.info().id();
+
+            set.for_each_ticks_mut(&mut |entity, ticks| f(entity, &component_type, ticks));
+        }
+    }
+
+    /// Drops all the sparse set components of the entity. Calls `on_removed` for every dropped component.
+    pub fn remove_entity(&mut self, entity: Entity, mut on_removed: impl FnMut(TypeId, ComponentHooks)) {
+        for (&component_type, set) in self.sets.iter_mut() {
+            if set.remove_entity(entity) {
+                on_removed(component_type, set.hooks());
+            }
+        }
+    }
+}
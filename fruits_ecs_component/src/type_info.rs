@@ -1,26 +1,104 @@
 use std::any::TypeId;
 
+use super::component::{Component, ComponentHooks};
+
+/// Key of a component type: a Rust type or a component registered at runtime.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum ComponentTypeId {
+    Type(TypeId),
+    /// Unique within the world that registered the component.
+    Dynamic(u32),
+}
+
+impl ComponentTypeId {
+    pub fn of<T: 'static>() -> Self {
+        Self::Type(TypeId::of::<T>())
+    }
+}
+
+impl From<TypeId> for ComponentTypeId {
+    fn from(type_id: TypeId) -> Self {
+        Self::Type(type_id)
+    }
+}
+
+/// Writes a clone of the value at `src` into the uninitialized `dst`.
+pub type CloneFn = unsafe fn(src: *const (), dst: *mut ());
+
+/// Clone function of `Clone` components, see `Component::CLONE`.
+///
+/// # Safety
+/// `src` should point to a valid `T` and `dst` to memory for a `T`.
+pub unsafe fn clone_component<T: Clone>(src: *const (), dst: *mut ()) {
+    (dst as *mut T).write((*(src as *const T)).clone());
+}
+
+/// Layout of a component defined at runtime.
+#[derive(Clone, Copy)]
+pub struct DynamicComponentDescriptor {
+    pub name: &'static str,
+    pub size: usize,
+    pub align: usize,
+    /// Drops the value in place. `None` if the value does not need dropping.
+    pub drop: Option<unsafe fn(*mut ())>,
+    /// `None` if the value cannot be cloned.
+    pub clone: Option<CloneFn>,
+}
+
 #[derive(Clone, Copy)]
 pub struct TypeInfo {
-    id: TypeId,
+    id: ComponentTypeId,
     name: &'static str,
     size: usize,
-    dropper: unsafe fn(*mut())
+    align: usize,
+    dropper: unsafe fn(*mut()),
+    cloner: Option<CloneFn>,
+    hooks: ComponentHooks,
 }
 
 impl TypeInfo {
     pub fn new<T: 'static>() -> Self {
         Self {
-            id: TypeId::of::<T>(),
+            id: ComponentTypeId::of::<T>(),
             name: std::any::type_name::<T>(),
             size: std::mem::size_of::<T>(),
+            align: std::mem::align_of::<T>(),
             dropper: Self::drop_any::<T>,
+            cloner: None,
+            hooks: ComponentHooks::EMPTY,
+        }
+    }
+
+    pub fn of_component<C: Component>() -> Self {
+        Self {
+            cloner: C::CLONE,
+            hooks: ComponentHooks::of::<C>(),
+            ..Self::new::<C>()
+        }
+    }
+
+    pub fn new_dynamic(id: u32, descriptor: &DynamicComponentDescriptor) -> Self {
+        assert!(descriptor.align.is_power_of_two(), "Component align should be a power of two. Component: {}.", descriptor.name);
+        assert!(descriptor.size.is_multiple_of(descriptor.align), "Component size should be a multiple of its align. Component: {}.", descriptor.name);
+
+        Self {
+            id: ComponentTypeId::Dynamic(id),
+            name: descriptor.name,
+            size: descriptor.size,
+            align: descriptor.align,
+            dropper: descriptor.drop.unwrap_or(Self::drop_nothing),
+            cloner: descriptor.clone,
+            hooks: ComponentHooks::EMPTY,
         }
     }
 
-    pub const fn id(&self) -> &TypeId { &self.id }
+    pub const fn id(&self) -> &ComponentTypeId { &self.id }
     pub const fn name(&self) -> &'static str { &self.name }
     pub const fn size(&self) -> usize { self.size }
+    pub const fn align(&self) -> usize { self.align }
+    pub const fn hooks(&self) -> &ComponentHooks { &self.hooks }
+    pub const fn cloner(&self) -> Option<CloneFn> { self.cloner }
     pub unsafe fn drop(&self, ptr: *mut()) { (self.dropper)(ptr) }
     unsafe fn drop_any<T>(ptr: *mut()) { std::ptr::drop_in_place(ptr as *mut T) }
+    unsafe fn drop_nothing(_ptr: *mut()) { }
 }
@@ -1,14 +1,13 @@
 use std::{
-    any::TypeId,
     collections::BTreeMap,
     hash::Hash
 };
 
-use super::{component::Component, type_info::TypeInfo};
+use super::{component::Component, type_info::{ComponentTypeId, TypeInfo}};
 
 #[derive(Clone)]
 pub struct UniqueComponentsSet {
-    component_infos: BTreeMap<TypeId, TypeInfo>,
+    component_infos: BTreeMap<ComponentTypeId, TypeInfo>,
 }
 
 impl UniqueComponentsSet {
@@ -18,17 +17,40 @@ impl UniqueComponentsSet {
         }
     }
 
-    pub fn component_infos(&self) -> &BTreeMap<TypeId, TypeInfo> {
+    pub fn component_infos(&self) -> &BTreeMap<ComponentTypeId, TypeInfo> {
         &self.component_infos
     }
 
     pub fn insert<C: Component>(&mut self) -> bool {
-        let info = TypeInfo::new::<C>();
+        self.insert_info(TypeInfo::of_component::<C>())
+    }
+
+    pub fn insert_info(&mut self, info: TypeInfo) -> bool {
         self.component_infos.insert(*info.id(), info).is_none()
     }
 
     pub fn remove<C: Component>(&mut self) -> bool {
-        self.component_infos.remove(&TypeId::of::<C>()).is_some()
+        self.remove_type(&ComponentTypeId::of::<C>())
+    }
+
+    pub fn remove_type(&mut self, component_type: &ComponentTypeId) -> bool {
+        self.component_infos.remove(component_type).is_some()
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        let mut component_infos = self.component_infos.clone();
+        component_infos.extend(other.component_infos.iter().map(|(id, info)| (*id, *info)));
+
+        Self { component_infos }
+    }
+
+    pub fn difference(&self, other: &Self) -> Self {
+        let component_infos = self.component_infos.iter()
+            .filter(|(id, _)| !other.component_infos.contains_key(id))
+            .map(|(id, info)| (*id, *info))
+            .collect();
+
+        Self { component_infos }
     }
 }
 
@@ -1,15 +1,23 @@
-use std::cell::UnsafeCell;
+use std::{
+    alloc::Layout,
+    cell::UnsafeCell,
+    ptr::NonNull,
+};
 
 pub const CHUNK_SIZE: usize = 1024 * 12;
+/// Minimal alignment of a chunk start. Cache line sized, so that SIMD types fit without extra padding.
+pub const CHUNK_MIN_ALIGN: usize = 64;
 
 pub struct ArchetypeItemPhysicalLocation {
     pub memory_offset: usize,
     pub memory_size: usize,
+    pub memory_align: usize,
     pub chunk_index: usize
 }
 
 pub struct UnsafeArchetype {
-    chunks: UnsafeCell<Vec<Box<[u8; CHUNK_SIZE]>>>,
+    chunks: UnsafeCell<Vec<NonNull<u8>>>,
+    chunk_layout: Layout,
 }
 
 // todo: temp
@@ -18,20 +26,21 @@ unsafe impl Send for UnsafeArchetype { }
 unsafe impl Sync for UnsafeArchetype { }
 
 impl UnsafeArchetype {
-    pub fn new() -> Self {
+    pub fn new(chunk_align: usize) -> Self {
         Self {
             chunks: UnsafeCell::new(Vec::new()),
+            chunk_layout: Layout::from_size_align(CHUNK_SIZE, chunk_align).unwrap(),
         }
     }
 
     pub unsafe fn get_memory(&self, location: &ArchetypeItemPhysicalLocation) -> (*mut (), usize) {
-        let chunks = &mut *self.chunks.get();
+        let chunks = &*self.chunks.get();
 
-        let chunk = &mut *chunks[location.chunk_index];
+        let chunk_ptr = chunks[location.chunk_index].as_ptr();
 
-        let chunk_ptr = chunk.as_mut_ptr();
+        let memory_ptr = chunk_ptr.add(location.memory_offset) as *mut ();
 
-        let memory_ptr = (chunk_ptr as usize + location.memory_offset) as *mut ();
+        debug_assert!((memory_ptr as usize).is_multiple_of(location.memory_align), "Archetype item memory is not aligned.");
 
         (memory_ptr, location.memory_size)
     }
@@ -42,15 +51,35 @@ impl UnsafeArchetype {
         chunks.len()
     }
 
+    pub fn chunk_layout(&self) -> Layout {
+        self.chunk_layout
+    }
+
     pub unsafe fn push_chunk(&mut self) {
         let chunks = &mut *self.chunks.get();
 
-        chunks.push(std::iter::repeat(0_u8).take(CHUNK_SIZE).collect::<Box<_>>().try_into().unwrap());
+        let chunk_ptr = std::alloc::alloc_zeroed(self.chunk_layout);
+
+        let Some(chunk_ptr) = NonNull::new(chunk_ptr) else {
+            std::alloc::handle_alloc_error(self.chunk_layout);
+        };
+
+        chunks.push(chunk_ptr);
     }
 
     pub unsafe fn pop_chunk(&mut self) {
         let chunks = &mut *self.chunks.get();
 
-        chunks.pop();
+        if let Some(chunk_ptr) = chunks.pop() {
+            std::alloc::dealloc(chunk_ptr.as_ptr(), self.chunk_layout);
+        }
     }
-}
\ No newline at end of file
+}
+
+impl Drop for UnsafeArchetype {
+    fn drop(&mut self) {
+        while self.chunks_count() > 0 {
+            unsafe { self.pop_chunk() };
+        }
+    }
+}
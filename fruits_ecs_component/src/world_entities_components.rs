@@ -1,72 +1,134 @@
-use std::{any::TypeId, marker::PhantomData};
+use std::{any::TypeId, collections::HashSet, marker::PhantomData, sync::{atomic::{AtomicU32, Ordering}, Arc}};
 
 use fruits_ecs_data_usage::PerTypeDataUsage;
+use fruits_utils::thread_pool::ThreadPool;
 
 use super::{
-    archetype::{Archetype, ArchetypeIteratorItem}, component::{Component, WorldArchetypes}, data_rw_lock::{DataRwLock, DataRwLockGuard},
-    entity::{Entity, EntityLocation, WorldEntities}, unique_components_set::UniqueComponentsSet,
+    archetype::{Archetype, ArchetypeIteratorItem, FetchContext}, bundle::Bundle, change_detection::{ComponentTicks, RunTicks, Tick, CHECK_TICK_THRESHOLD}, command_queue::CommandQueue,
+    component::{Component, StorageType, WorldArchetypes}, component_id::ComponentId, data_rw_lock::{DataRwLock, DataRwLockGuard}, disabled::Disabled, entity::{Entity, EntityLocation, WorldEntities},
+    query_filter::{Filtered, QueryFilter}, query_par_iter::QueryParIter, query_state::QueryState, removed_components::RemovedComponentsLog, required_components::RequiredComponents, snapshot::EntitiesComponentsSnapshot, sparse_set::WorldSparseSets, type_info::{ComponentTypeId, DynamicComponentDescriptor}, unique_components_set::UniqueComponentsSet,
 };
 
 pub struct WorldEntitiesComponents {
     archetypes: WorldArchetypes,
     entity_datas: WorldEntities,
+    sparse_sets: WorldSparseSets,
     locks: DataRwLock,
+    change_tick: AtomicU32,
+    /// The tick of the last `check_change_ticks` pass.
+    last_check_tick: Tick,
+    removed_components: RemovedComponentsLog,
+    /// Commands of the component hooks, applied at the end of every structural change.
+    hook_commands: CommandQueue,
 }
 
-pub struct WorldEntitiesComponentsQuery<'w, A: ArchetypeIteratorItem> {
+pub struct WorldEntitiesComponentsQuery<'w, A: ArchetypeIteratorItem, F: QueryFilter = ()> {
     archetype_indices: Box<[usize]>,
     archetypes: &'w WorldArchetypes,
     entities: &'w WorldEntities,
+    sparse_sets: &'w WorldSparseSets,
+    ticks: RunTicks,
     _guards: Box<[DataRwLockGuard<'w>]>,
     _phantom: PhantomData<fn(A::Item<'static>) -> A::Item<'static>>,
+    _filter: PhantomData<fn() -> F>,
 }
 
-impl<'w, A: ArchetypeIteratorItem> WorldEntitiesComponentsQuery<'w, A> {
+impl<'w, A: ArchetypeIteratorItem, F: QueryFilter> WorldEntitiesComponentsQuery<'w, A, F> {
     fn new_unchecked(
         archetype_indices: Box<[usize]>,
         guards: Box<[DataRwLockGuard<'w>]>,
-        archetypes: &'w WorldArchetypes,
-        entities: &'w WorldEntities,
+        entities_components: &'w WorldEntitiesComponents,
+        ticks: RunTicks,
     ) -> Self {
         Self {
             archetype_indices,
-            archetypes,
-            entities,
+            archetypes: &entities_components.archetypes,
+            entities: &entities_components.entity_datas,
+            sparse_sets: &entities_components.sparse_sets,
+            ticks,
             _guards: guards,
             _phantom: Default::default(),
+            _filter: Default::default(),
         }
     }
     
     pub fn iter<'r>(&'r self) -> impl Iterator<Item = <A::ReadOnlyItem<'static> as ArchetypeIteratorItem>::Item<'w>> + 'r
         where 'w: 'r
     {
-        self.archetype_indices.iter()
-            .copied()
-            .map(|i| self.archetypes.by_id_ref(i).unwrap())
-            .flat_map(move |a| a.iter::<A::ReadOnlyItem<'static>>())
+        let (sparse_sets, ticks) = (self.sparse_sets, self.ticks);
+
+        self.archetypes_iter()
+            .flat_map(move |a| a.iter::<Filtered<A::ReadOnlyItem<'static>, F>>(sparse_sets, ticks))
     }
     
     pub fn iter_mut<'r>(&'r mut self) -> impl Iterator<Item = <A::Item<'static> as ArchetypeIteratorItem>::Item<'w>> + 'r
         where 'w: 'r
     {
-        self.archetype_indices.iter()
-            .copied()
-            .map(|i| self.archetypes.by_id_ref(i).unwrap())
-            .flat_map(move |a| a.iter::<A::Item<'static>>())
+        let (sparse_sets, ticks) = (self.sparse_sets, self.ticks);
+
+        self.archetypes_iter()
+            .flat_map(move |a| a.iter::<Filtered<A::Item<'static>, F>>(sparse_sets, ticks))
+    }
+
+    /// Iterates over archetype chunks, yielding contiguous item slices of every chunk.
+    /// Does not compile for queries with sparse set components or row filters.
+    pub fn chunks<'r>(&'r self) -> impl Iterator<Item = <A::ReadOnlyItem<'static> as ArchetypeIteratorItem>::Slice<'w>> + 'r
+        where 'w: 'r
+    {
+        let (sparse_sets, ticks) = (self.sparse_sets, self.ticks);
+
+        self.archetypes_iter()
+            .flat_map(move |a| a.iter_chunks::<Filtered<A::ReadOnlyItem<'static>, F>>(sparse_sets, ticks))
+    }
+
+    /// Iterates over archetype chunks, yielding contiguous item slices of every chunk.
+    /// Does not compile for queries with sparse set components or row filters.
+    pub fn chunks_mut<'r>(&'r mut self) -> impl Iterator<Item = <A::Item<'static> as ArchetypeIteratorItem>::Slice<'w>> + 'r
+        where 'w: 'r
+    {
+        let (sparse_sets, ticks) = (self.sparse_sets, self.ticks);
+
+        self.archetypes_iter()
+            .flat_map(move |a| a.iter_chunks::<Filtered<A::Item<'static>, F>>(sparse_sets, ticks))
+    }
+
+    pub fn par_iter<'r>(&'r self, thread_pool: &'r ThreadPool) -> QueryParIter<'r, 'w, A::ReadOnlyItem<'static>, F> {
+        QueryParIter::new(&self.archetype_indices, self.archetypes, self.sparse_sets, self.ticks, thread_pool)
+    }
+
+    pub fn par_iter_mut<'r>(&'r mut self, thread_pool: &'r ThreadPool) -> QueryParIter<'r, 'w, A::Item<'static>, F> {
+        QueryParIter::new(&self.archetype_indices, self.archetypes, self.sparse_sets, self.ticks, thread_pool)
     }
 
     pub fn len(&self) -> usize {
+        if !Filtered::<A, F>::IS_DENSE {
+            return self.iter().count();
+        }
+
         self.archetypes_iter()
             .map(|a| a.entities_count())
             .sum()
     }
 
     pub fn is_empty(&self) -> bool {
+        if !Filtered::<A, F>::IS_DENSE {
+            return self.iter().next().is_none();
+        }
+
         !self.archetypes_iter().any(|a| a.entities_count() > 0)
     }
 
     pub fn get<'a>(&'a self, entity: Entity) -> Option<<A::ReadOnlyItem<'static> as ArchetypeIteratorItem>::Item<'w>> {
-        if TypeId::of::<<A::ReadOnlyItem<'static> as ArchetypeIteratorItem>::Item<'static>>() == TypeId::of::<Entity>() {
+        let location = self.entities.get(entity)?;
+
+        let archetype = self.archetypes.by_id_ref(location.archetype_id)?;
+
+        // The same check the matched archetypes passed, so the entities with the `Disabled` marker are also skipped unless the query includes them.
+        if !Filtered::<A, F>::matches_archetype(archetype) {
+            return None;
+        }
+
+        if TypeId::of::<<A::ReadOnlyItem<'static> as ArchetypeIteratorItem>::Item<'static>>() == TypeId::of::<Entity>() && TypeId::of::<F>() == TypeId::of::<()>() {
             let item = unsafe {
                 std::mem::transmute_copy::<_, <A::ReadOnlyItem<'static> as ArchetypeIteratorItem>::Item<'w>>(&entity)
             };
@@ -74,19 +136,23 @@ impl<'w, A: ArchetypeIteratorItem> WorldEntitiesComponentsQuery<'w, A> {
             return Some(item);
         }
 
+        Filtered::<A::ReadOnlyItem<'static>, F>::from_archetype(
+            self.fetch_context(archetype),
+            location.entity_archetype_index,
+        )
+    }
+
+    pub fn get_mut<'a>(&'a mut self, entity: Entity) -> Option<<A::Item<'static> as ArchetypeIteratorItem>::Item<'w>> {
         let location = self.entities.get(entity)?;
 
         let archetype = self.archetypes.by_id_ref(location.archetype_id)?;
 
-        Some(<A::ReadOnlyItem<'static> as ArchetypeIteratorItem>::from_archetype(
-            location.entity_archetype_index,
-            unsafe { archetype.unsafe_archetype() },
-            archetype.layout(),
-        ))
-    }
+        // The same check the matched archetypes passed, so the entities with the `Disabled` marker are also skipped unless the query includes them.
+        if !Filtered::<A, F>::matches_archetype(archetype) {
+            return None;
+        }
 
-    pub fn get_mut<'a>(&'a mut self, entity: Entity) -> Option<<A::Item<'static> as ArchetypeIteratorItem>::Item<'w>> {
-        if TypeId::of::<<A::Item<'static> as ArchetypeIteratorItem>::Item<'static>>() == TypeId::of::<Entity>() {
+        if TypeId::of::<<A::Item<'static> as ArchetypeIteratorItem>::Item<'static>>() == TypeId::of::<Entity>() && TypeId::of::<F>() == TypeId::of::<()>() {
             let item = unsafe {
                 std::mem::transmute_copy::<_, <A::Item<'static> as ArchetypeIteratorItem>::Item<'w>>(&entity)
             };
@@ -94,15 +160,18 @@ impl<'w, A: ArchetypeIteratorItem> WorldEntitiesComponentsQuery<'w, A> {
             return Some(item);
         }
 
-        let location = self.entities.get(entity)?;
-
-        let archetype = self.archetypes.by_id_ref(location.archetype_id)?;
-
-        Some(<A::Item<'static> as ArchetypeIteratorItem>::from_archetype(
+        Filtered::<A::Item<'static>, F>::from_archetype(
+            self.fetch_context(archetype),
             location.entity_archetype_index,
-            unsafe { archetype.unsafe_archetype() },
-            archetype.layout(),
-        ))
+        )
+    }
+
+    fn fetch_context(&self, archetype: &'w Archetype) -> FetchContext<'w> {
+        FetchContext {
+            archetype,
+            sparse_sets: self.sparse_sets,
+            ticks: self.ticks,
+        }
     }
 
     fn archetypes_iter<'r>(&'r self) -> impl Iterator<Item = &'w Archetype> + 'r
@@ -118,74 +187,141 @@ impl WorldEntitiesComponents {
         Self {
             archetypes: WorldArchetypes::new(),
             entity_datas: WorldEntities::new(),
+            sparse_sets: WorldSparseSets::new(),
             locks: DataRwLock::new(),
+            // Systems running for the first time see every component as added, so real ticks start after zero.
+            change_tick: AtomicU32::new(1),
+            last_check_tick: Tick::new(1),
+            removed_components: RemovedComponentsLog::new(),
+            hook_commands: CommandQueue::new(),
         }
     }
 
-    pub fn query<'w, A: ArchetypeIteratorItem>(&'w self) -> WorldEntitiesComponentsQuery<'w, A> {
-        let mut usage = PerTypeDataUsage::new();
+    /// Changes made outside of systems are marked with this tick.
+    pub fn change_tick(&self) -> Tick {
+        Tick::new(self.change_tick.load(Ordering::Acquire))
+    }
 
-        A::fill_usage(&mut usage);
+    /// Returns the current tick for a system run and moves the world to the next one,
+    /// so that later changes made outside of systems are newer than the run.
+    pub fn increment_change_tick(&self) -> Tick {
+        Tick::new(self.change_tick.fetch_add(1, Ordering::AcqRel))
+    }
 
-        let guards = self.locks.lock_by_type_usage(&usage).unwrap();
-        
-        let mut components = usage.into_values();
+    /// Clamps all the component ticks, so they do not look newer once the counter wraps around them.
+    /// Does nothing until `CHECK_TICK_THRESHOLD` ticks have passed since the previous pass.
+    /// Returns the tick the ticks are clamped against, which the system last run ticks should be clamped against as well.
+    pub fn check_change_ticks(&mut self) -> Option<Tick> {
+        let tick = self.change_tick();
 
-        components.remove(&TypeId::of::<Entity>());
+        if tick.get().wrapping_sub(self.last_check_tick.get()) < CHECK_TICK_THRESHOLD {
+            return None;
+        }
 
-        if components.len() == 0 {
-            return WorldEntitiesComponentsQuery::new_unchecked(
-                (0..self.archetypes.all().len()).collect::<Box<_>>(),
-                guards,
-                &self.archetypes,
-                &self.entity_datas,
-            );
+        for archetype in self.archetypes.all_mut() {
+            archetype.check_change_ticks(tick);
         }
 
-        let archetypes_with_rarest_component = components
-            .keys()
-            .map(|c| self.archetypes.ids_by_component(c))
-            .flatten()
-            .min_by_key(|a| a.len());
+        self.sparse_sets.check_change_ticks(tick);
+        self.removed_components.check_change_ticks(tick);
+
+        self.last_check_tick = tick;
+
+        Some(tick)
+    }
+
+    pub fn query<'w, A: ArchetypeIteratorItem>(&'w self) -> WorldEntitiesComponentsQuery<'w, A> {
+        self.query_filtered::<A, ()>(RunTicks {
+            last_run: Tick::default(),
+            this_run: self.change_tick(),
+        })
+    }
+
+    /// Matches only the archetypes created since the previous query with the same state.
+    pub fn query_with_state<'w, A: ArchetypeIteratorItem, F: QueryFilter>(
+        &'w self,
+        state: &mut QueryState<A::Item<'static>, F>,
+        ticks: RunTicks,
+    ) -> WorldEntitiesComponentsQuery<'w, A, F> {
+        let guards = self.lock_query::<A, F>();
+
+        state.update(&self.archetypes);
+
+        WorldEntitiesComponentsQuery::new_unchecked(
+            state.archetype_indices().into(),
+            guards,
+            self,
+            ticks,
+        )
+    }
+
+    pub fn query_filtered<'w, A: ArchetypeIteratorItem, F: QueryFilter>(&'w self, ticks: RunTicks) -> WorldEntitiesComponentsQuery<'w, A, F> {
+        let guards = self.lock_query::<A, F>();
+        
+        let mut components = HashSet::new();
+
+        Filtered::<A, F>::fill_archetype_components(&mut components);
 
-        let Some(archetypes_with_rarest_component) = archetypes_with_rarest_component else {
+        // Archetypes can only contain registered components.
+        let Some(components) = self.archetypes.components().bits_by_types(&components) else {
             return WorldEntitiesComponentsQuery::new_unchecked(
                 Box::new([]),
-                Box::new([]),
-                &self.archetypes,
-                &self.entity_datas,
+                guards,
+                self,
+                ticks,
             );
         };
 
-        let mut suitable_archetypes = Vec::new();
-
-        for archetype in archetypes_with_rarest_component.iter() {
-            let contains_all_components = components.keys().all(|c| {
-                let Some(archetypes_with_component) = self.archetypes.ids_by_component(c) else {
-                    return false;
-                };
-
-                archetypes_with_component.contains(archetype)
-            });
-
-            if contains_all_components {
-                suitable_archetypes.push(*archetype);
-            }
-        }
+        let suitable_archetypes = self.archetypes.ids_with_components(&components)
+            .filter(|id| Filtered::<A, F>::matches_archetype(self.archetypes.by_id_ref(*id).unwrap()))
+            .collect::<Box<_>>();
 
         WorldEntitiesComponentsQuery::new_unchecked(
-            suitable_archetypes.into_boxed_slice(),
+            suitable_archetypes,
             guards,
-            &self.archetypes,
-            &self.entity_datas,
+            self,
+            ticks,
         )
     }
 
+    fn lock_query<A: ArchetypeIteratorItem, F: QueryFilter>(&self) -> Box<[DataRwLockGuard<'_>]> {
+        let mut usage = PerTypeDataUsage::new();
+
+        Filtered::<A, F>::fill_usage(&mut usage);
+
+        self.locks.lock_by_type_usage(&usage).unwrap()
+    }
+
     pub fn entities_count(&self) -> usize {
         self.entity_datas.len()
     }
 
+    /// Gives out an entity without `&mut`, like commands do while systems run.
+    /// The entity is created without components by `flush_reserved_entities`, which every entity creation and command application does first.
+    pub fn reserve_entity(&self) -> Entity {
+        self.entity_datas.reserve_entity()
+    }
+
+    pub fn flush_reserved_entities(&mut self) {
+        let archetype_id = self.archetypes.id_by_components_or_create(UniqueComponentsSet::new()).0;
+
+        let archetype = self.archetypes.by_id_mut(archetype_id).unwrap();
+
+        self.entity_datas.flush_reserved(|entity| {
+            let entity_archetype_index = archetype.entities_count();
+
+            archetype.create_entity(entity);
+
+            EntityLocation {
+                archetype_id,
+                entity_archetype_index,
+            }
+        });
+    }
+
     pub fn create_entity(&mut self) -> Entity {
+        self.flush_reserved_entities();
+
         let archetype_id = self.archetypes.id_by_components_or_create(UniqueComponentsSet::new()).0;
 
         let archetype = self.archetypes.by_id_mut(archetype_id).unwrap();
@@ -203,10 +339,20 @@ impl WorldEntitiesComponents {
     }
 
     pub fn destroy_entity(&mut self, entity: Entity) -> bool {
+        let destroyed = self.destroy_entity_without_commands(entity);
+
+        self.apply_hook_commands();
+
+        destroyed
+    }
+
+    fn destroy_entity_without_commands(&mut self, entity: Entity) -> bool {
         let Some(entity_location) = self.entity_datas.remove(entity) else {
             return false;
         };
 
+        self.run_destroy_hooks(entity, entity_location.archetype_id);
+
         let archetype = self.archetypes.by_id_mut(entity_location.archetype_id).unwrap();
 
         let last_entity = archetype.destroy_entity(entity_location.entity_archetype_index).unwrap();
@@ -218,96 +364,1433 @@ impl WorldEntitiesComponents {
         return true;
     }
 
-    pub fn add_component<C: Component>(&mut self, entity: Entity, component: C) -> Option<C> {
-        let entity_location = self.entity_datas.get(entity)?;
+    /// Drops the sparse set components of the entity and records the removal of all its components.
+    fn run_destroy_hooks(&mut self, entity: Entity, archetype_id: usize) {
+        let tick = self.change_tick();
+        let removed_components = &mut self.removed_components;
+        let hook_commands = &mut self.hook_commands;
 
-        let src_archetype_id = entity_location.archetype_id;
-        
-        let mut dst_components_set = {
-            let src_archetype = self.archetypes.by_id_ref(src_archetype_id).unwrap();
+        self.sparse_sets.remove_entity(entity, |c, hooks| {
+            (hooks.on_remove)(entity, hook_commands);
+            removed_components.push(c.into(), entity, tick);
+        });
 
-            src_archetype.components_set().clone()
-        };
+        let archetype = self.archetypes.by_id_ref(archetype_id).unwrap();
 
-        if !dst_components_set.insert::<C>() {
-            return Some(component);
+        for (&component_type, info) in archetype.components_set().component_infos() {
+            (info.hooks().on_remove)(entity, hook_commands);
+            removed_components.push(component_type, entity, tick);
         }
+    }
 
-        let dst_archetype_id = self.archetypes.id_by_components_or_create(dst_components_set).0;
+    /// Applies the commands queued by component hooks, including the ones queued while applying.
+    fn apply_hook_commands(&mut self) {
+        while !self.hook_commands.is_empty() {
+            let mut commands = std::mem::take(&mut self.hook_commands);
 
-        // len 0 1
-        // len 1 1
-        // todo: what?
-        let (src_archetype, dst_archetype) = self.archetypes.by_2_ids_mut((src_archetype_id, dst_archetype_id)).unwrap();
+            commands.apply(self);
+        }
+    }
 
-        let entity_with_added_component_new_location = EntityLocation {
-            archetype_id: dst_archetype_id,
-            entity_archetype_index: dst_archetype.entities_count(),
-        };
+    /// Creates the entity directly in the archetype of the bundle.
+    pub fn spawn<B: Bundle>(&mut self, bundle: B) -> Entity {
+        self.flush_reserved_entities();
 
-        let last_entity = Archetype::add_component(src_archetype, dst_archetype, entity_location.entity_archetype_index, component).ok().unwrap();
+        let tick = self.change_tick();
 
-        if last_entity != entity {
-            *self.entity_datas.get_mut(last_entity).unwrap() = *entity_location;
+        let empty_archetype_id = self.archetypes.id_by_components_or_create(UniqueComponentsSet::new()).0;
+        let archetype_id = self.archetypes.id_with_bundle::<B>(empty_archetype_id);
+
+        let archetype = self.archetypes.by_id_mut(archetype_id).unwrap();
+
+        let entity_archetype_index = archetype.entities_count();
+
+        let entity = self.entity_datas.insert(EntityLocation {
+            archetype_id,
+            entity_archetype_index,
+        });
+
+        archetype.create_entity(entity);
+
+        unsafe { bundle.write(archetype, entity_archetype_index, entity, &mut self.sparse_sets, tick) };
+
+        let required = self.archetypes.required_bundle_components::<B>();
+
+        self.write_required(&required, empty_archetype_id, archetype_id, entity_archetype_index, entity);
+
+        B::on_add(entity, &mut self.hook_commands);
+
+        self.apply_hook_commands();
+
+        entity
+    }
+
+    /// Registers all the entities and allocates their rows at once, then writes the bundles.
+    pub fn spawn_batch<B: Bundle>(&mut self, bundles: impl IntoIterator<Item = B>) -> Vec<Entity> {
+        let bundles = bundles.into_iter().collect::<Vec<_>>();
+
+        self.flush_reserved_entities();
+
+        let tick = self.change_tick();
+
+        let empty_archetype_id = self.archetypes.id_by_components_or_create(UniqueComponentsSet::new()).0;
+        let archetype_id = self.archetypes.id_with_bundle::<B>(empty_archetype_id);
+
+        let archetype = self.archetypes.by_id_mut(archetype_id).unwrap();
+
+        let first_entity_archetype_index = archetype.entities_count();
+
+        let entities = self.entity_datas.insert_batch((0..bundles.len()).map(|i| EntityLocation {
+            archetype_id,
+            entity_archetype_index: first_entity_archetype_index + i,
+        }));
+
+        archetype.create_entities(&entities);
+
+        let required = self.archetypes.required_bundle_components::<B>();
+
+        for (i, (bundle, &entity)) in bundles.into_iter().zip(entities.iter()).enumerate() {
+            let entity_archetype_index = first_entity_archetype_index + i;
+
+            let archetype = self.archetypes.by_id_mut(archetype_id).unwrap();
+
+            unsafe { bundle.write(archetype, entity_archetype_index, entity, &mut self.sparse_sets, tick) };
+
+            self.write_required(&required, empty_archetype_id, archetype_id, entity_archetype_index, entity);
+
+            B::on_add(entity, &mut self.hook_commands);
         }
 
-        *self.entity_datas.get_mut(entity).unwrap() = entity_with_added_component_new_location;
+        self.apply_hook_commands();
 
-        return None;
+        entities
     }
 
-    pub fn remove_component<C: Component>(&mut self, entity: Entity) -> Option<C> {
-        let entity_location = self.entity_datas.get(entity)?;
+    /// Returns the count of destroyed entities. Missing and repeated entities are skipped.
+    /// Each archetype is compacted once for all of its destroyed entities.
+    pub fn despawn_batch(&mut self, entities: &[Entity]) -> usize {
+        let mut locations = entities.iter()
+            .filter_map(|&e| Some((e, *self.entity_datas.get(e)?)))
+            .collect::<Vec<_>>();
 
-        let src_archetype_id = entity_location.archetype_id;
+        locations.sort_unstable_by_key(|(e, l)| (l.archetype_id, *e));
+        locations.dedup_by_key(|(e, _)| *e);
 
-        let mut dst_components_set = {
-            let src_archetype = self.archetypes.by_id_ref(src_archetype_id).unwrap();
+        for group in locations.chunk_by(|(_, a), (_, b)| a.archetype_id == b.archetype_id) {
+            let archetype_id = group[0].1.archetype_id;
 
-            src_archetype.components_set().clone()
-        };
+            for &(entity, _) in group {
+                self.entity_datas.remove(entity);
+                self.run_destroy_hooks(entity, archetype_id);
+            }
 
-        if !dst_components_set.remove::<C>() {
-            return None;
+            let entity_indices = group.iter().map(|(_, l)| l.entity_archetype_index).collect::<Vec<_>>();
+
+            let moved = self.archetypes.by_id_mut(archetype_id).unwrap().destroy_entities(&entity_indices);
+
+            for (entity, entity_archetype_index) in moved {
+                self.entity_datas.get_mut(entity).unwrap().entity_archetype_index = entity_archetype_index;
+            }
         }
 
-        let dst_archetype_id = self.archetypes.id_by_components_or_create(dst_components_set).0;
+        self.apply_hook_commands();
 
-        let (src_archetype, dst_archetype) = self.archetypes.by_2_ids_mut((src_archetype_id, dst_archetype_id)).unwrap();
+        locations.len()
+    }
 
-        let entity_with_removed_component_new_location = EntityLocation {
-            archetype_id: dst_archetype_id,
-            entity_archetype_index: dst_archetype.entities_count(),
+    /// Names of the stored components that cannot be cloned, which prevent taking snapshots.
+    pub fn uncloneable_stored_components(&self) -> Vec<&'static str> {
+        let mut names = self.archetypes.all().iter()
+            .filter(|a| a.entities_count() > 0)
+            .flat_map(|a| a.components_set().component_infos().values())
+            .filter(|info| info.cloner().is_none())
+            .map(|info| info.name())
+            .chain(self.sparse_sets.uncloneable_infos().map(|info| info.name()))
+            .collect::<Vec<_>>();
+
+        names.sort_unstable();
+        names.dedup();
+        names
+    }
+
+    /// Clones all the entities and components. Returns the names of the stored components that cannot be cloned on failure.
+    pub fn snapshot(&self) -> Result<EntitiesComponentsSnapshot, Vec<&'static str>> {
+        let uncloneable_components = self.uncloneable_stored_components();
+
+        if !uncloneable_components.is_empty() {
+            return Err(uncloneable_components);
+        }
+
+        Ok(EntitiesComponentsSnapshot {
+            archetypes: self.archetypes.all().iter().map(|a| a.try_clone().unwrap()).collect(),
+            entities: self.entity_datas.clone(),
+            sparse_sets: self.sparse_sets.try_clone().unwrap(),
+        })
+    }
+
+    /// Brings back the entities with the same ids and their components, dropping the current ones.
+    /// Hooks are not run, the change tick keeps going. The snapshot should be taken from this world.
+    /// Restored components are marked as changed, and as added if the entity did not have them,
+    /// components the restore drops are logged as removed.
+    pub fn restore(&mut self, snapshot: &EntitiesComponentsSnapshot) {
+        let tick = self.change_tick();
+
+        let mut dropped = self.entity_component_types();
+
+        for (id, archetype) in self.archetypes.all_mut().iter_mut().enumerate() {
+            archetype.clear();
+
+            let Some(snapshot_archetype) = snapshot.archetypes.get(id) else {
+                continue;
+            };
+
+            assert!(Arc::ptr_eq(archetype.layout(), snapshot_archetype.layout()), "The snapshot is taken from another world.");
+
+            *archetype = snapshot_archetype.try_clone().unwrap();
+        }
+
+        self.entity_datas = snapshot.entities.clone();
+        self.sparse_sets = snapshot.sparse_sets.try_clone().unwrap();
+
+        let mut mark_restored = |entity: Entity, component_type: &ComponentTypeId, ticks: &mut ComponentTicks| {
+            ticks.changed = tick;
+
+            if !dropped.remove(&(entity, *component_type)) {
+                ticks.added = tick;
+            }
         };
 
-        let (last_entity, component) = Archetype::remove_component(src_archetype, dst_archetype, entity_location.entity_archetype_index).unwrap();
+        for archetype in self.archetypes.all_mut() {
+            archetype.for_each_ticks_mut(&mut mark_restored);
+        }
 
-        if last_entity != entity {
-            *self.entity_datas.get_mut(last_entity).unwrap() = *entity_location;
+        self.sparse_sets.for_each_ticks_mut(&mut mark_restored);
+
+        for (entity, component_type) in dropped {
+            self.removed_components.push(component_type, entity, tick);
         }
+    }
 
-        *self.entity_datas.get_mut(entity).unwrap() = entity_with_removed_component_new_location;
+    /// Pairs of every entity and the types of its components.
+    fn entity_component_types(&self) -> HashSet<(Entity, ComponentTypeId)> {
+        let mut pairs = HashSet::new();
 
-        return Some(component);
+        for archetype in self.archetypes.all() {
+            let component_types = archetype.components_set().component_infos().keys();
+
+            for entity_index in 0..archetype.entities_count() {
+                let entity = archetype.get_entity(entity_index).unwrap();
+
+                pairs.extend(component_types.clone().map(|component_type| (entity, *component_type)));
+            }
+        }
+
+        pairs.extend(self.sparse_sets.entity_component_types());
+        pairs
     }
 
-    pub fn get_component<C: Component>(&self, entity: Entity) -> Option<&C> {
-        let entity_location = self.entity_datas.get(entity)?;
+    /// Names of the entity components that cannot be cloned.
+    pub fn uncloneable_components(&self, entity: Entity) -> Vec<&'static str> {
+        let Some(entity_location) = self.entity_datas.get(entity) else {
+            return Vec::new();
+        };
 
         let archetype = self.archetypes.by_id_ref(entity_location.archetype_id).unwrap();
 
-        archetype.get_component_ref::<C>(entity_location.entity_archetype_index)
+        archetype.components_set().component_infos().values().copied()
+            .chain(self.sparse_sets.entity_infos(entity))
+            .filter(|info| info.cloner().is_none())
+            .map(|info| info.name())
+            .collect()
     }
 
-    pub fn get_component_mut<C: Component>(&mut self, entity: Entity) -> Option<&mut C> {
-        let entity_location = self.entity_datas.get(entity)?;
+    /// Creates an entity with clones of all the entity components in the same archetype.
+    /// Returns `None` if the entity does not exist or has components listed by `uncloneable_components`.
+    pub fn clone_entity(&mut self, entity: Entity) -> Option<Entity> {
+        let &entity_location = self.entity_datas.get(entity)?;
 
-        let archetype = self.archetypes.by_id_ref(entity_location.archetype_id).unwrap();
+        if !self.uncloneable_components(entity).is_empty() {
+            return None;
+        }
 
-        archetype.get_component_mut::<C>(entity_location.entity_archetype_index)
+        self.flush_reserved_entities();
+
+        let tick = self.change_tick();
+
+        let archetype_id = entity_location.archetype_id;
+        let archetype = self.archetypes.by_id_mut(archetype_id).unwrap();
+
+        let clone = self.entity_datas.insert(EntityLocation {
+            archetype_id,
+            entity_archetype_index: archetype.entities_count(),
+        });
+
+        archetype.clone_entity(entity_location.entity_archetype_index, clone, tick).unwrap();
+
+        self.sparse_sets.clone_entity(entity, clone, tick);
+
+        let infos = archetype.components_set().component_infos().values().copied()
+            .chain(self.sparse_sets.entity_infos(clone))
+            .collect::<Vec<_>>();
+
+        for info in infos.iter() {
+            (info.hooks().on_add)(clone, &mut self.hook_commands);
+        }
+
+        for info in infos.iter() {
+            (info.hooks().on_insert)(clone, &mut self.hook_commands);
+        }
+
+        self.apply_hook_commands();
+
+        Some(clone)
     }
 
-    pub fn contains_entity(&self, entity: Entity) -> bool {
-        self.entity_datas.contains(entity)
+    /// Returns the bundle back if the entity does not exist or already has any of the bundle components.
+    pub fn insert_bundle<B: Bundle>(&mut self, entity: Entity, bundle: B) -> Option<B> {
+        let Some(&entity_location) = self.entity_datas.get(entity) else {
+            return Some(bundle);
+        };
+
+        let src_archetype_id = entity_location.archetype_id;
+
+        if B::contains_any(self.archetypes.by_id_ref(src_archetype_id).unwrap(), entity, &self.sparse_sets) {
+            return Some(bundle);
+        }
+
+        let dst_archetype_id = self.archetypes.id_with_bundle::<B>(src_archetype_id);
+
+        let dst_entity_index = self.move_entity(entity, entity_location, dst_archetype_id);
+
+        let dst_archetype = self.archetypes.by_id_ref(dst_archetype_id).unwrap();
+
+        let tick = self.change_tick();
+
+        unsafe { bundle.write(dst_archetype, dst_entity_index, entity, &mut self.sparse_sets, tick) };
+
+        let required = self.archetypes.required_bundle_components::<B>();
+
+        self.write_required(&required, src_archetype_id, dst_archetype_id, dst_entity_index, entity);
+
+        B::on_add(entity, &mut self.hook_commands);
+
+        self.apply_hook_commands();
+
+        None
+    }
+
+    /// Returns `None` if the entity does not exist or misses any of the bundle components.
+    pub fn remove_bundle<B: Bundle>(&mut self, entity: Entity) -> Option<B> {
+        let entity_location = *self.entity_datas.get(entity)?;
+
+        let src_archetype_id = entity_location.archetype_id;
+        let src_archetype = self.archetypes.by_id_ref(src_archetype_id).unwrap();
+
+        if !B::contains_all(src_archetype, entity, &self.sparse_sets) {
+            return None;
+        }
+
+        let bundle = unsafe { B::read(src_archetype, entity_location.entity_archetype_index, entity, &mut self.sparse_sets) };
+
+        let mut table_components = UniqueComponentsSet::new();
+        let mut sparse_components = HashSet::new();
+
+        B::fill_components(&mut table_components, &mut sparse_components);
+
+        let tick = self.change_tick();
+
+        for component_type in table_components.component_infos().keys().copied().chain(sparse_components.iter().map(|&c| c.into())) {
+            self.removed_components.push(component_type, entity, tick);
+        }
+
+        let dst_archetype_id = self.archetypes.id_without_bundle::<B>(src_archetype_id);
+
+        self.move_entity(entity, entity_location, dst_archetype_id);
+
+        B::on_remove(entity, &mut self.hook_commands);
+
+        self.apply_hook_commands();
+
+        Some(bundle)
+    }
+
+    /// Writes the default values of the required components the entity did not have in the `src_archetype_id` archetype.
+    fn write_required(&mut self, required: &RequiredComponents, src_archetype_id: usize, dst_archetype_id: usize, dst_entity_index: usize, entity: Entity) {
+        if required.is_empty() {
+            return;
+        }
+
+        let tick = self.change_tick();
+
+        let src_archetype = self.archetypes.by_id_ref(src_archetype_id).unwrap();
+        let dst_archetype = self.archetypes.by_id_ref(dst_archetype_id).unwrap();
+
+        // `id_with_bundle` added the required table components to the destination archetype, and the move left them uninitialized.
+        unsafe { required.write_missing((src_archetype, dst_archetype), dst_entity_index, entity, &mut self.sparse_sets, tick, &mut self.hook_commands) };
+    }
+
+    /// Moves the entity row into the `dst_archetype_id` archetype and fixes up the locations. Returns the new entity index.
+    fn move_entity(&mut self, entity: Entity, entity_location: EntityLocation, dst_archetype_id: usize) -> usize {
+        if entity_location.archetype_id == dst_archetype_id {
+            return entity_location.entity_archetype_index;
+        }
+
+        let (src_archetype, dst_archetype) = self.archetypes.by_2_ids_mut((entity_location.archetype_id, dst_archetype_id)).unwrap();
+
+        let (dst_entity_index, last_entity) = unsafe { Archetype::move_entity(src_archetype, dst_archetype, entity_location.entity_archetype_index) };
+
+        if last_entity != entity {
+            *self.entity_datas.get_mut(last_entity).unwrap() = entity_location;
+        }
+
+        *self.entity_datas.get_mut(entity).unwrap() = EntityLocation {
+            archetype_id: dst_archetype_id,
+            entity_archetype_index: dst_entity_index,
+        };
+
+        dst_entity_index
+    }
+
+    /// Returns the component back if the entity already has one.
+    pub fn add_component<C: Component>(&mut self, entity: Entity, component: C) -> Option<C> {
+        if !self.contains_entity(entity) {
+            return None;
+        }
+
+        // Required components have to be written in the same archetype move.
+        if !self.archetypes.required_components::<C>().is_empty() {
+            return self.insert_bundle(entity, (component,)).map(|(c,)| c);
+        }
+
+        if let Some(component) = self.add_component_without_hooks(entity, component) {
+            return Some(component);
+        }
+
+        C::on_add(entity, &mut self.hook_commands);
+        C::on_insert(entity, &mut self.hook_commands);
+
+        self.apply_hook_commands();
+
+        None
+    }
+
+    /// Adds the component or overwrites the existing one, returning the old value.
+    pub fn insert_component<C: Component>(&mut self, entity: Entity, component: C) -> Option<C> {
+        let Some(existing) = self.get_component_mut::<C>(entity) else {
+            self.add_component(entity, component);
+            return None;
+        };
+
+        let old = std::mem::replace(existing, component);
+
+        C::on_insert(entity, &mut self.hook_commands);
+
+        self.apply_hook_commands();
+
+        Some(old)
+    }
+
+    fn add_component_without_hooks<C: Component>(&mut self, entity: Entity, component: C) -> Option<C> {
+        let entity_location = self.entity_datas.get(entity)?;
+
+        if C::STORAGE_TYPE == StorageType::SparseSet {
+            let tick = self.change_tick();
+
+            return self.sparse_sets.get_or_create_mut::<C>().insert(entity, component, tick);
+        }
+
+        let src_archetype_id = entity_location.archetype_id;
+
+        let Some(dst_archetype_id) = self.archetypes.id_with_component::<C>(src_archetype_id) else {
+            return Some(component);
+        };
+
+        // len 0 1
+        // len 1 1
+        // todo: what?
+        let tick = self.change_tick();
+
+        let (src_archetype, dst_archetype) = self.archetypes.by_2_ids_mut((src_archetype_id, dst_archetype_id)).unwrap();
+
+        let entity_with_added_component_new_location = EntityLocation {
+            archetype_id: dst_archetype_id,
+            entity_archetype_index: dst_archetype.entities_count(),
+        };
+
+        let last_entity = Archetype::add_component(src_archetype, dst_archetype, entity_location.entity_archetype_index, component, tick).ok().unwrap();
+
+        if last_entity != entity {
+            *self.entity_datas.get_mut(last_entity).unwrap() = *entity_location;
+        }
+
+        *self.entity_datas.get_mut(entity).unwrap() = entity_with_added_component_new_location;
+
+        return None;
+    }
+
+    pub fn remove_component<C: Component>(&mut self, entity: Entity) -> Option<C> {
+        let component = self.remove_component_without_hooks::<C>(entity)?;
+
+        C::on_remove(entity, &mut self.hook_commands);
+
+        self.apply_hook_commands();
+
+        Some(component)
+    }
+
+    fn remove_component_without_hooks<C: Component>(&mut self, entity: Entity) -> Option<C> {
+        let entity_location = self.entity_datas.get(entity)?;
+
+        if C::STORAGE_TYPE == StorageType::SparseSet {
+            let component = self.sparse_sets.get_mut::<C>()?.remove(entity)?;
+
+            self.removed_components.push(ComponentTypeId::of::<C>(), entity, self.change_tick());
+
+            return Some(component);
+        }
+
+        let src_archetype_id = entity_location.archetype_id;
+
+        let dst_archetype_id = self.archetypes.id_without_component::<C>(src_archetype_id)?;
+
+        let (src_archetype, dst_archetype) = self.archetypes.by_2_ids_mut((src_archetype_id, dst_archetype_id)).unwrap();
+
+        let entity_with_removed_component_new_location = EntityLocation {
+            archetype_id: dst_archetype_id,
+            entity_archetype_index: dst_archetype.entities_count(),
+        };
+
+        let (last_entity, component) = Archetype::remove_component(src_archetype, dst_archetype, entity_location.entity_archetype_index).unwrap();
+
+        if last_entity != entity {
+            *self.entity_datas.get_mut(last_entity).unwrap() = *entity_location;
+        }
+
+        *self.entity_datas.get_mut(entity).unwrap() = entity_with_removed_component_new_location;
+
+        self.removed_components.push(ComponentTypeId::of::<C>(), entity, self.change_tick());
+
+        return Some(component);
+    }
+
+    pub fn get_component<C: Component>(&self, entity: Entity) -> Option<&C> {
+        let entity_location = self.entity_datas.get(entity)?;
+
+        if C::STORAGE_TYPE == StorageType::SparseSet {
+            return self.sparse_sets.get::<C>()?.get(entity);
+        }
+
+        let archetype = self.archetypes.by_id_ref(entity_location.archetype_id).unwrap();
+
+        archetype.get_component_ref::<C>(entity_location.entity_archetype_index)
+    }
+
+    /// Marks the component as changed at the current change tick.
+    pub fn get_component_mut<C: Component>(&mut self, entity: Entity) -> Option<&mut C> {
+        let entity_location = self.entity_datas.get(entity)?;
+
+        let tick = self.change_tick();
+
+        if C::STORAGE_TYPE == StorageType::SparseSet {
+            let sparse_set = self.sparse_sets.get_mut::<C>()?;
+
+            unsafe { (*sparse_set.get_ticks_ptr(entity)?).changed = tick };
+
+            return sparse_set.get_mut(entity);
+        }
+
+        let archetype = self.archetypes.by_id_ref(entity_location.archetype_id).unwrap();
+
+        unsafe { (*archetype.get_component_ticks_ptr::<C>(entity_location.entity_archetype_index)?).changed = tick };
+
+        archetype.get_component_mut::<C>(entity_location.entity_archetype_index)
+    }
+
+    /// Entities that lost the component between the run ticks, including destroyed ones.
+    pub fn removed_components<C: Component>(&self, ticks: RunTicks) -> impl Iterator<Item = Entity> + '_ {
+        self.removed_components.iter(&ComponentTypeId::of::<C>(), ticks)
+    }
+
+    /// Forgets the removals made before `tick`.
+    pub fn clear_removed_components(&mut self, tick: Tick) {
+        self.removed_components.clear_before(tick);
+    }
+
+    pub fn get_component_ticks<C: Component>(&self, entity: Entity) -> Option<ComponentTicks> {
+        let entity_location = self.entity_datas.get(entity)?;
+
+        if C::STORAGE_TYPE == StorageType::SparseSet {
+            return self.sparse_sets.get::<C>()?.get_ticks(entity);
+        }
+
+        let archetype = self.archetypes.by_id_ref(entity_location.archetype_id).unwrap();
+
+        archetype.get_component_ticks::<C>(entity_location.entity_archetype_index)
+    }
+
+    /// Registers a component type defined at runtime. Its values are accessed as bytes by the returned id.
+    pub fn register_dynamic_component(&mut self, descriptor: DynamicComponentDescriptor) -> ComponentId {
+        self.archetypes.components_mut().register_dynamic(&descriptor)
+    }
+
+    /// Adds the component from its bytes or overwrites the existing one, dropping the old value.
+    /// Returns `false` if the entity does not exist or the id is not registered.
+    /// Works with any component stored in archetype tables. Required components are not added.
+    ///
+    /// # Safety
+    /// The bytes should be a valid value of the component. The world takes its ownership.
+    pub unsafe fn insert_by_id(&mut self, entity: Entity, id: ComponentId, component: &[u8]) -> bool {
+        let Some(&entity_location) = self.entity_datas.get(entity) else {
+            return false;
+        };
+
+        let Some(&info) = self.archetypes.components().info(id) else {
+            return false;
+        };
+
+        assert_eq!(component.len(), info.size(), "Component size mismatch. Component: {}.", info.name());
+
+        let tick = self.change_tick();
+
+        let src_archetype = self.archetypes.by_id_ref(entity_location.archetype_id).unwrap();
+
+        let is_added = !src_archetype.contains_component(info.id());
+
+        if is_added {
+            let dst_archetype_id = self.archetypes.id_with_component_info(entity_location.archetype_id, info).unwrap();
+
+            self.move_entity(entity, entity_location, dst_archetype_id);
+        }
+
+        let &entity_location = self.entity_datas.get(entity).unwrap();
+        let archetype = self.archetypes.by_id_ref(entity_location.archetype_id).unwrap();
+
+        let component_ptr = archetype.get_component_ptr_by_id(entity_location.entity_archetype_index, info.id()).unwrap();
+        let ticks_ptr = archetype.get_component_ticks_ptr_by_id(entity_location.entity_archetype_index, info.id()).unwrap();
+
+        match is_added {
+            true => *ticks_ptr = ComponentTicks::new(tick),
+            false => {
+                info.drop(component_ptr);
+                (*ticks_ptr).changed = tick;
+            },
+        }
+
+        std::ptr::copy_nonoverlapping(component.as_ptr(), component_ptr as *mut u8, component.len());
+
+        if is_added {
+            (info.hooks().on_add)(entity, &mut self.hook_commands);
+        }
+
+        (info.hooks().on_insert)(entity, &mut self.hook_commands);
+
+        self.apply_hook_commands();
+
+        true
+    }
+
+    /// Bytes of the component stored in archetype tables.
+    pub fn get_by_id(&self, entity: Entity, id: ComponentId) -> Option<&[u8]> {
+        let entity_location = self.entity_datas.get(entity)?;
+        let info = self.archetypes.components().info(id)?;
+
+        let archetype = self.archetypes.by_id_ref(entity_location.archetype_id).unwrap();
+
+        let component_ptr = archetype.get_component_ptr_by_id(entity_location.entity_archetype_index, info.id())?;
+
+        Some(unsafe { std::slice::from_raw_parts(component_ptr as *const u8, info.size()) })
+    }
+
+    /// Marks the component as changed at the current change tick.
+    ///
+    /// # Safety
+    /// The written bytes should stay a valid value of the component.
+    pub unsafe fn get_by_id_mut(&mut self, entity: Entity, id: ComponentId) -> Option<&mut [u8]> {
+        let entity_location = self.entity_datas.get(entity)?;
+        let info = self.archetypes.components().info(id)?;
+
+        let tick = self.change_tick();
+
+        let archetype = self.archetypes.by_id_ref(entity_location.archetype_id).unwrap();
+
+        let component_ptr = archetype.get_component_ptr_by_id(entity_location.entity_archetype_index, info.id())?;
+
+        (*archetype.get_component_ticks_ptr_by_id(entity_location.entity_archetype_index, info.id()).unwrap()).changed = tick;
+
+        Some(std::slice::from_raw_parts_mut(component_ptr as *mut u8, info.size()))
+    }
+
+    /// Drops the component stored in archetype tables. Returns `false` if the entity does not have it.
+    pub fn remove_by_id(&mut self, entity: Entity, id: ComponentId) -> bool {
+        let Some(&entity_location) = self.entity_datas.get(entity) else {
+            return false;
+        };
+
+        let Some(&info) = self.archetypes.components().info(id) else {
+            return false;
+        };
+
+        let src_archetype = self.archetypes.by_id_ref(entity_location.archetype_id).unwrap();
+
+        let Some(component_ptr) = src_archetype.get_component_ptr_by_id(entity_location.entity_archetype_index, info.id()) else {
+            return false;
+        };
+
+        unsafe { info.drop(component_ptr) };
+
+        let dst_archetype_id = self.archetypes.id_without_component_type(entity_location.archetype_id, *info.id()).unwrap();
+
+        self.move_entity(entity, entity_location, dst_archetype_id);
+
+        self.removed_components.push(*info.id(), entity, self.change_tick());
+
+        (info.hooks().on_remove)(entity, &mut self.hook_commands);
+
+        self.apply_hook_commands();
+
+        true
+    }
+
+    pub fn contains_entity(&self, entity: Entity) -> bool {
+        self.entity_datas.contains(entity)
+    }
+
+    /// Whether the entity has the `Disabled` marker, so queries skip it by default.
+    pub fn is_disabled(&self, entity: Entity) -> bool {
+        self.get_component::<Disabled>(entity).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{clone_component, Added, Changed, CloneFn, IncludeDisabled, With, Without, Or};
+
+    struct Position(u32);
+    impl Component for Position { }
+
+    struct Velocity(u32);
+    impl Component for Velocity { }
+
+    struct Tag(u32);
+    impl Component for Tag {
+        const STORAGE_TYPE: StorageType = StorageType::SparseSet;
+    }
+
+    #[test]
+    fn chunks_cover_every_entity_once() {
+        let mut entities_components = WorldEntitiesComponents::new();
+
+        for i in 0..5000 {
+            let entity = entities_components.create_entity();
+            entities_components.add_component(entity, Position(i));
+
+            if i % 3 == 0 {
+                entities_components.add_component(entity, Velocity(1));
+            }
+        }
+
+        let query = entities_components.query::<(Entity, &Position)>();
+
+        let mut chunks_count = 0;
+        let mut positions = Vec::new();
+
+        for (entities, chunk_positions) in query.chunks() {
+            assert_eq!(entities.len(), chunk_positions.len());
+            chunks_count += 1;
+            positions.extend(chunk_positions.iter().map(|p| p.0));
+        }
+
+        positions.sort();
+
+        assert!(chunks_count > 2);
+        assert_eq!(positions, (0..5000).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn chunks_mut_writes_are_visible_by_entity() {
+        let mut entities_components = WorldEntitiesComponents::new();
+
+        let entities = (0..3000).map(|i| {
+            let entity = entities_components.create_entity();
+            entities_components.add_component(entity, Position(i));
+            entities_components.add_component(entity, Velocity(2));
+            entity
+        }).collect::<Vec<_>>();
+
+        {
+            let mut query = entities_components.query::<(&Velocity, &mut Position)>();
+
+            for (velocities, positions) in query.chunks_mut() {
+                for (velocity, position) in velocities.iter().zip(positions) {
+                    position.0 += velocity.0;
+                }
+            }
+        }
+
+        for (i, &entity) in entities.iter().enumerate() {
+            assert_eq!(entities_components.get_component::<Position>(entity).unwrap().0, i as u32 + 2);
+        }
+    }
+
+    #[test]
+    fn sparse_set_component_does_not_change_archetype() {
+        let mut entities_components = WorldEntitiesComponents::new();
+
+        let entity = entities_components.create_entity();
+        entities_components.add_component(entity, Position(0));
+
+        let archetype_id = entities_components.entity_datas.get(entity).unwrap().archetype_id;
+
+        assert!(entities_components.add_component(entity, Tag(7)).is_none());
+        assert_eq!(entities_components.entity_datas.get(entity).unwrap().archetype_id, archetype_id);
+        assert_eq!(entities_components.get_component::<Tag>(entity).map(|t| t.0), Some(7));
+
+        assert_eq!(entities_components.remove_component::<Tag>(entity).map(|t| t.0), Some(7));
+        assert_eq!(entities_components.entity_datas.get(entity).unwrap().archetype_id, archetype_id);
+        assert!(entities_components.get_component::<Tag>(entity).is_none());
+    }
+
+    #[test]
+    fn query_mixes_table_and_sparse_set_components() {
+        let mut entities_components = WorldEntitiesComponents::new();
+
+        let entities = (0..100).map(|i| {
+            let entity = entities_components.create_entity();
+            entities_components.add_component(entity, Position(i));
+
+            if i % 2 == 0 {
+                entities_components.add_component(entity, Tag(i));
+            }
+
+            entity
+        }).collect::<Vec<_>>();
+
+        entities_components.destroy_entity(entities[0]);
+
+        {
+            let mut query = entities_components.query::<(&mut Position, &Tag)>();
+
+            assert_eq!(query.len(), 49);
+
+            for (position, tag) in query.iter_mut() {
+                assert_eq!(position.0, tag.0);
+                position.0 += 1000;
+            }
+
+            assert!(query.get(entities[1]).is_none());
+            assert_eq!(query.get(entities[2]).map(|(p, _)| p.0), Some(1002));
+        }
+
+        assert_eq!(entities_components.get_component::<Position>(entities[3]).unwrap().0, 3);
+    }
+
+    #[test]
+    fn archetype_transitions_are_cached_both_ways() {
+        let mut entities_components = WorldEntitiesComponents::new();
+
+        let entity = entities_components.create_entity();
+        entities_components.add_component(entity, Position(1));
+
+        let src_id = entities_components.entity_datas.get(entity).unwrap().archetype_id;
+
+        let velocity = ComponentTypeId::of::<Velocity>();
+
+        let dst_id = entities_components.archetypes.id_with_component::<Velocity>(src_id).unwrap();
+
+        let edges = &entities_components.archetypes.edges;
+        assert_eq!(edges[src_id].with_component.get(&velocity), Some(&Some(dst_id)));
+        assert_eq!(edges[dst_id].without_component.get(&velocity), Some(&Some(src_id)));
+
+        assert_eq!(entities_components.archetypes.id_without_component::<Velocity>(dst_id), Some(src_id));
+        assert_eq!(entities_components.archetypes.id_with_component::<Velocity>(dst_id), None);
+        assert_eq!(entities_components.archetypes.id_without_component::<Velocity>(src_id), None);
+
+        let edges = &entities_components.archetypes.edges;
+        assert_eq!(edges[dst_id].with_component.get(&velocity), Some(&None));
+        assert_eq!(edges[src_id].without_component.get(&velocity), Some(&None));
+
+        let archetypes_count = entities_components.archetypes.edges.len();
+
+        for i in 0..10 {
+            entities_components.add_component(entity, Velocity(i));
+            assert_eq!(entities_components.entity_datas.get(entity).unwrap().archetype_id, dst_id);
+
+            assert_eq!(entities_components.remove_component::<Velocity>(entity).unwrap().0, i);
+            assert_eq!(entities_components.entity_datas.get(entity).unwrap().archetype_id, src_id);
+        }
+
+        assert_eq!(entities_components.archetypes.edges.len(), archetypes_count);
+        assert_eq!(entities_components.archetypes.edges[src_id].with_component.len(), 1);
+        assert_eq!(entities_components.archetypes.edges[dst_id].without_component.len(), 1);
+
+        assert_eq!(entities_components.get_component::<Position>(entity).unwrap().0, 1);
+    }
+
+    #[test]
+    fn spawn_goes_straight_to_bundle_archetype() {
+        let mut entities_components = WorldEntitiesComponents::new();
+
+        let entity = entities_components.spawn((Position(1), Velocity(2), Tag(3)));
+
+        assert_eq!(entities_components.archetypes.all().len(), 2);
+        assert_eq!(entities_components.get_component::<Position>(entity).unwrap().0, 1);
+        assert_eq!(entities_components.get_component::<Velocity>(entity).unwrap().0, 2);
+        assert_eq!(entities_components.get_component::<Tag>(entity).unwrap().0, 3);
+    }
+
+    #[test]
+    fn bundle_insert_and_remove_round_trip() {
+        let mut entities_components = WorldEntitiesComponents::new();
+
+        let entities = (0..10).map(|i| entities_components.spawn((Position(i),))).collect::<Vec<_>>();
+
+        assert!(entities_components.insert_bundle(entities[3], (Velocity(30), Tag(300))).is_none());
+        assert!(entities_components.insert_bundle(entities[3], (Velocity(31),)).is_some());
+
+        assert!(entities_components.remove_bundle::<(Velocity, Position)>(entities[4]).is_none());
+
+        let (velocity, tag) = entities_components.remove_bundle::<(Velocity, Tag)>(entities[3]).unwrap();
+
+        assert_eq!((velocity.0, tag.0), (30, 300));
+        assert!(entities_components.get_component::<Tag>(entities[3]).is_none());
+
+        for (i, entity) in entities.into_iter().enumerate() {
+            assert_eq!(entities_components.get_component::<Position>(entity).unwrap().0, i as u32);
+        }
+    }
+
+    #[test]
+    fn spawn_batch_and_despawn_batch() {
+        let mut entities_components = WorldEntitiesComponents::new();
+
+        let entities = entities_components.spawn_batch((0..3000).map(|i| (Position(i), Tag(i))));
+
+        assert_eq!(entities_components.entities_count(), 3000);
+
+        let despawned = entities.iter().copied().filter(|e| e.version_index().index % 2 == 0).collect::<Vec<_>>();
+
+        assert_eq!(entities_components.despawn_batch(&[&despawned[..], &despawned[..10]].concat()), 1500);
+        assert_eq!(entities_components.entities_count(), 1500);
+
+        for (i, &entity) in entities.iter().enumerate() {
+            match i % 2 == 0 {
+                true => assert!(!entities_components.contains_entity(entity)),
+                false => {
+                    assert_eq!(entities_components.get_component::<Position>(entity).unwrap().0, i as u32);
+                    assert_eq!(entities_components.get_component::<Tag>(entity).unwrap().0, i as u32);
+                },
+            }
+        }
+    }
+
+    #[test]
+    fn spawn_batch_registers_rows_after_existing_ones() {
+        let mut entities_components = WorldEntitiesComponents::new();
+
+        let existing = entities_components.spawn_batch((0..3).map(|i| (Position(i),)));
+        entities_components.destroy_entity(existing[1]);
+
+        let entities = entities_components.spawn_batch((3..6).map(|i| (Position(i),)));
+
+        // The freed id is reused by the first entity of the batch.
+        assert_eq!(entities[0].version_index().index, existing[1].version_index().index);
+
+        for (i, &entity) in entities.iter().enumerate() {
+            let location = entities_components.entity_datas.get(entity).unwrap();
+
+            assert_eq!(location.entity_archetype_index, i + 2);
+            assert_eq!(entities_components.get_component::<Position>(entity).unwrap().0, i as u32 + 3);
+        }
+
+        let query = entities_components.query::<(Entity, &Position)>();
+        assert_eq!(query.iter().map(|(_, p)| p.0).collect::<Vec<_>>(), [0, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn despawn_batch_fills_holes_with_last_rows_per_archetype() {
+        let mut entities_components = WorldEntitiesComponents::new();
+
+        let entities = (0..10).map(|i| match i % 2 == 0 {
+            true => entities_components.spawn((Position(i),)),
+            false => entities_components.spawn((Position(i), Velocity(i))),
+        }).collect::<Vec<_>>();
+
+        let archetype_id = entities_components.entity_datas.get(entities[0]).unwrap().archetype_id;
+
+        // Rows 0, 2 and 4 of the `Position` archetype: only row 0 is a hole below the new count, filled by row 3.
+        let moved = entities_components.archetypes.by_id_mut(archetype_id).unwrap().destroy_entities(&[4, 0, 2, 2, 9]);
+        assert_eq!(moved, vec![(entities[6], 0)]);
+
+        let mut entities_components = WorldEntitiesComponents::new();
+
+        let entities = (0..10).map(|i| match i % 2 == 0 {
+            true => entities_components.spawn((Position(i),)),
+            false => entities_components.spawn((Position(i), Velocity(i))),
+        }).collect::<Vec<_>>();
+
+        assert_eq!(entities_components.despawn_batch(&[entities[0], entities[4], entities[1], entities[9], entities[4]]), 4);
+
+        for (i, &entity) in entities.iter().enumerate() {
+            match [0, 1, 4, 9].contains(&i) {
+                true => assert!(!entities_components.contains_entity(entity)),
+                false => assert_eq!(entities_components.get_component::<Position>(entity).unwrap().0, i as u32),
+            }
+        }
+
+        let query = entities_components.query::<(Entity, &Position)>();
+        assert_eq!(query.iter().count(), 6);
+        assert!(query.iter().all(|(e, p)| entities[p.0 as usize] == e));
+    }
+
+    #[test]
+    fn checked_change_ticks_do_not_match_after_wrapping() {
+        let mut entities_components = WorldEntitiesComponents::new();
+
+        let entity = entities_components.spawn((Position(0), Tag(0)));
+
+        let mut last_run = entities_components.increment_change_tick();
+
+        // A system that keeps running while the counter wraps around the component ticks.
+        for _ in 0..9 {
+            entities_components.change_tick.fetch_add(CHECK_TICK_THRESHOLD, Ordering::AcqRel);
+
+            let tick = entities_components.check_change_ticks().unwrap();
+            last_run.check_tick(tick);
+
+            let ticks = RunTicks { last_run, this_run: entities_components.increment_change_tick() };
+
+            assert_eq!(entities_components.query_filtered::<Entity, Added<Position>>(ticks).len(), 0);
+            assert_eq!(entities_components.query_filtered::<Entity, Changed<Tag>>(ticks).len(), 0);
+
+            last_run = ticks.this_run;
+        }
+
+        assert!(entities_components.change_tick().get() < CHECK_TICK_THRESHOLD, "The counter should wrap.");
+        assert_eq!(entities_components.check_change_ticks(), None);
+
+        entities_components.get_component_mut::<Position>(entity);
+
+        let ticks = RunTicks { last_run, this_run: entities_components.increment_change_tick() };
+
+        assert_eq!(entities_components.query_filtered::<Entity, Changed<Position>>(ticks).iter().collect::<Vec<_>>(), [entity]);
+    }
+
+    #[test]
+    fn optional_fetches_and_filters() {
+        let mut entities_components = WorldEntitiesComponents::new();
+
+        for i in 0..300 {
+            match i % 3 {
+                0 => entities_components.spawn((Position(i),)),
+                1 => entities_components.spawn((Position(i), Velocity(i))),
+                _ => entities_components.spawn((Position(i), Tag(i))),
+            };
+        }
+
+        let query = entities_components.query::<(&Position, Option<&Velocity>, Option<&Tag>)>();
+
+        assert_eq!(query.len(), 300);
+
+        for (position, velocity, tag) in query.iter() {
+            assert_eq!(velocity.map(|v| v.0), (position.0 % 3 == 1).then_some(position.0));
+            assert_eq!(tag.map(|t| t.0), (position.0 % 3 == 2).then_some(position.0));
+        }
+
+        drop(query);
+
+        let ticks = RunTicks { last_run: Tick::new(0), this_run: entities_components.change_tick() };
+
+        let without = entities_components.query_filtered::<&Position, (Without<Velocity>, Without<Tag>)>(ticks);
+        assert!(without.iter().all(|p| p.0 % 3 == 0));
+        assert_eq!(without.len(), 100);
+        drop(without);
+
+        let with = entities_components.query_filtered::<&Position, With<Tag>>(ticks);
+        assert!(with.iter().all(|p| p.0 % 3 == 2));
+        assert_eq!(with.len(), 100);
+        drop(with);
+
+        let or = entities_components.query_filtered::<&Position, Or<(With<Velocity>, With<Tag>)>>(ticks);
+        assert!(or.iter().all(|p| p.0 % 3 != 0));
+        assert_eq!(or.len(), 200);
+    }
+
+    #[test]
+    fn par_iter_mut_visits_every_entity_once() {
+        let mut entities_components = WorldEntitiesComponents::new();
+
+        entities_components.spawn_batch((0..5000).map(|i| (Position(i), Velocity(0))));
+        entities_components.spawn_batch((5000..6000).map(|i| (Position(i),)));
+
+        let thread_pool = ThreadPool::new(3);
+
+        let mut query = entities_components.query::<(&Position, &mut Velocity)>();
+
+        query.par_iter_mut(&thread_pool).batch_size(100).for_each(|(position, velocity)| {
+            velocity.0 += position.0 + 1;
+        });
+
+        let sum = std::sync::atomic::AtomicU32::new(0);
+
+        query.par_iter(&thread_pool).for_each(|(position, velocity)| {
+            assert_eq!(velocity.0, position.0 + 1);
+            sum.fetch_add(1, Ordering::Relaxed);
+        });
+
+        assert_eq!(sum.into_inner(), 5000);
+    }
+
+    #[test]
+    fn query_state_matches_new_archetypes() {
+        let mut entities_components = WorldEntitiesComponents::new();
+        let mut state = QueryState::<&'static Position, Without<Tag>>::new();
+
+        entities_components.spawn((Position(0),));
+        entities_components.spawn((Velocity(1),));
+
+        let ticks = RunTicks { last_run: Tick::new(0), this_run: entities_components.change_tick() };
+
+        assert_eq!(entities_components.query_with_state::<&Position, Without<Tag>>(&mut state, ticks).len(), 1);
+
+        entities_components.spawn((Position(2), Velocity(2)));
+        entities_components.spawn((Position(3), Tag(3)));
+
+        let query = entities_components.query_with_state::<&Position, Without<Tag>>(&mut state, ticks);
+
+        let mut positions = query.iter().map(|p| p.0).collect::<Vec<_>>();
+        positions.sort();
+
+        assert_eq!(positions, [0, 2]);
+        // Tag is sparse, so the tagged entity shares the archetype of the first one.
+        assert_eq!(state.archetype_indices().len(), 2);
+    }
+
+    #[test]
+    fn hooks_run_on_add_insert_and_remove() {
+        use std::sync::atomic::AtomicUsize;
+
+        static INSERTS_COUNT: AtomicUsize = AtomicUsize::new(0);
+        static REMOVES_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+        struct Hooked(u32);
+        impl Component for Hooked {
+            fn on_add(entity: Entity, commands: &mut CommandQueue) {
+                commands.add_component(entity, Velocity(7));
+            }
+
+            fn on_insert(_entity: Entity, _commands: &mut CommandQueue) {
+                INSERTS_COUNT.fetch_add(1, Ordering::Relaxed);
+            }
+
+            fn on_remove(entity: Entity, commands: &mut CommandQueue) {
+                REMOVES_COUNT.fetch_add(1, Ordering::Relaxed);
+                commands.remove_component::<Velocity>(entity);
+            }
+        }
+
+        let mut entities_components = WorldEntitiesComponents::new();
+
+        let entity = entities_components.spawn((Position(0), Hooked(0)));
+
+        assert_eq!(entities_components.get_component::<Velocity>(entity).unwrap().0, 7);
+        assert_eq!(INSERTS_COUNT.load(Ordering::Relaxed), 1);
+
+        assert_eq!(entities_components.insert_component(entity, Hooked(1)).unwrap().0, 0);
+        assert_eq!(INSERTS_COUNT.load(Ordering::Relaxed), 2);
+
+        entities_components.remove_component::<Hooked>(entity);
+
+        assert!(entities_components.get_component::<Velocity>(entity).is_none());
+        assert_eq!(REMOVES_COUNT.load(Ordering::Relaxed), 1);
+
+        entities_components.add_component(entity, Hooked(2));
+        entities_components.destroy_entity(entity);
+
+        assert_eq!(INSERTS_COUNT.load(Ordering::Relaxed), 3);
+        assert_eq!(REMOVES_COUNT.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn required_components_are_inserted_with_defaults() {
+        #[derive(Default)]
+        struct Speed(u32);
+        impl Component for Speed { }
+
+        #[derive(Default)]
+        struct Visible(bool);
+        impl Component for Visible {
+            const STORAGE_TYPE: StorageType = StorageType::SparseSet;
+        }
+
+        struct Mover;
+        impl Component for Mover {
+            fn register_required(required: &mut RequiredComponents) {
+                required.require::<Speed>();
+                required.require::<Visible>();
+            }
+        }
+
+        let mut entities_components = WorldEntitiesComponents::new();
+
+        let spawned = entities_components.spawn((Mover, Speed(5)));
+
+        assert_eq!(entities_components.get_component::<Speed>(spawned).unwrap().0, 5);
+        assert!(!entities_components.get_component::<Visible>(spawned).unwrap().0);
+
+        let added = entities_components.spawn((Position(0),));
+
+        assert!(entities_components.add_component(added, Mover).is_none());
+        assert_eq!(entities_components.get_component::<Speed>(added).unwrap().0, 0);
+        assert!(entities_components.get_component::<Visible>(added).is_some());
+        assert!(entities_components.add_component(added, Mover).is_some());
+
+        assert_eq!(entities_components.query::<(&Mover, &Speed, &Position)>().len(), 1);
+
+        // Removing the component keeps the required ones.
+        entities_components.remove_component::<Mover>(added);
+
+        assert!(entities_components.get_component::<Speed>(added).is_some());
+        assert!(entities_components.get_component::<Position>(added).is_some());
+    }
+
+    #[test]
+    fn dynamic_components_are_stored_as_bytes() {
+        use std::sync::atomic::AtomicUsize;
+
+        static DROPPED_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+        unsafe fn count_drop(_ptr: *mut ()) {
+            DROPPED_COUNT.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let mut entities_components = WorldEntitiesComponents::new();
+
+        let health = entities_components.register_dynamic_component(DynamicComponentDescriptor {
+            name: "Health",
+            size: 4,
+            align: 4,
+            drop: Some(count_drop),
+            clone: None,
+        });
+
+        let entities = entities_components.spawn_batch((0..3).map(|i| (Position(i),)));
+
+        for (i, &entity) in entities.iter().enumerate() {
+            assert!(unsafe { entities_components.insert_by_id(entity, health, &(i as u32 * 10).to_ne_bytes()) });
+        }
+
+        assert_eq!(entities_components.get_by_id(entities[1], health).unwrap(), 10u32.to_ne_bytes());
+        assert_eq!(entities_components.query::<&Position>().len(), 3);
+        assert_eq!(entities_components.get_component::<Position>(entities[2]).unwrap().0, 2);
+
+        // Overwriting drops the old value in place.
+        assert!(unsafe { entities_components.insert_by_id(entities[1], health, &11u32.to_ne_bytes()) });
+        assert_eq!(entities_components.get_by_id(entities[1], health).unwrap(), 11u32.to_ne_bytes());
+        assert_eq!(DROPPED_COUNT.load(Ordering::Relaxed), 1);
+
+        assert!(entities_components.remove_by_id(entities[0], health));
+        assert!(!entities_components.remove_by_id(entities[0], health));
+        assert!(entities_components.get_by_id(entities[0], health).is_none());
+        assert_eq!(entities_components.get_by_id(entities[2], health).unwrap(), 20u32.to_ne_bytes());
+        assert_eq!(DROPPED_COUNT.load(Ordering::Relaxed), 2);
+
+        entities_components.destroy_entity(entities[2]);
+
+        assert_eq!(DROPPED_COUNT.load(Ordering::Relaxed), 3);
+        assert_eq!(entities_components.get_by_id(entities[1], health).unwrap(), 11u32.to_ne_bytes());
+    }
+
+    #[test]
+    fn clone_entity_copies_cloneable_components() {
+        #[derive(Clone)]
+        struct Name(String);
+        impl Component for Name {
+            const CLONE: Option<CloneFn> = Some(clone_component::<Self>);
+        }
+
+        #[derive(Clone)]
+        struct Label(&'static str);
+        impl Component for Label {
+            const STORAGE_TYPE: StorageType = StorageType::SparseSet;
+            const CLONE: Option<CloneFn> = Some(clone_component::<Self>);
+        }
+
+        let mut entities_components = WorldEntitiesComponents::new();
+
+        let template = entities_components.spawn((Name("boid".to_string()), Label("template")));
+        let clone = entities_components.clone_entity(template).unwrap();
+
+        assert_ne!(clone, template);
+        assert_eq!(entities_components.get_component::<Name>(clone).unwrap().0, "boid");
+        assert_eq!(entities_components.get_component::<Label>(clone).unwrap().0, "template");
+        assert_eq!(entities_components.query::<&Name>().len(), 2);
+
+        entities_components.get_component_mut::<Name>(clone).unwrap().0.push('2');
+
+        assert_eq!(entities_components.get_component::<Name>(template).unwrap().0, "boid");
+
+        entities_components.add_component(template, Position(0));
+
+        assert_eq!(entities_components.uncloneable_components(template), [std::any::type_name::<Position>()]);
+        assert!(entities_components.clone_entity(template).is_none());
+        assert_eq!(entities_components.query::<&Name>().len(), 2);
+    }
+
+    #[test]
+    fn restore_brings_back_entities_with_same_ids() {
+        #[derive(Clone)]
+        struct Health(u32);
+        impl Component for Health {
+            const CLONE: Option<CloneFn> = Some(clone_component::<Self>);
+        }
+
+        #[derive(Clone)]
+        struct Stunned;
+        impl Component for Stunned {
+            const STORAGE_TYPE: StorageType = StorageType::SparseSet;
+            const CLONE: Option<CloneFn> = Some(clone_component::<Self>);
+        }
+
+        let mut entities_components = WorldEntitiesComponents::new();
+
+        let entities = entities_components.spawn_batch((0..4).map(|i| (Health(i),)));
+
+        entities_components.destroy_entity(entities[0]);
+        entities_components.add_component(entities[1], Stunned);
+
+        let snapshot = entities_components.snapshot().ok().unwrap();
+
+        entities_components.get_component_mut::<Health>(entities[2]).unwrap().0 = 100;
+        entities_components.destroy_entity(entities[1]);
+        entities_components.remove_component::<Stunned>(entities[1]);
+        let spawned = entities_components.spawn((Health(5),));
+
+        entities_components.restore(&snapshot);
+
+        assert!(!entities_components.contains_entity(spawned));
+        assert!(!entities_components.contains_entity(entities[0]));
+        assert_eq!(entities_components.get_component::<Health>(entities[2]).unwrap().0, 2);
+        assert!(entities_components.get_component::<Stunned>(entities[1]).is_some());
+        assert_eq!(entities_components.query::<&Health>().len(), 3);
+
+        // The free list is restored too, so the next entity gets the same id again.
+        assert_eq!(entities_components.spawn((Health(5),)), spawned);
+
+        entities_components.add_component(entities[2], Position(0));
+
+        assert_eq!(entities_components.snapshot().err().unwrap(), [std::any::type_name::<Position>()]);
+    }
+
+    #[test]
+    fn restore_marks_restored_components_and_logs_dropped_ones() {
+        #[derive(Clone)]
+        struct Health(#[allow(dead_code)] u32);
+        impl Component for Health {
+            const CLONE: Option<CloneFn> = Some(clone_component::<Self>);
+        }
+
+        #[derive(Clone)]
+        struct Stunned;
+        impl Component for Stunned {
+            const STORAGE_TYPE: StorageType = StorageType::SparseSet;
+            const CLONE: Option<CloneFn> = Some(clone_component::<Self>);
+        }
+
+        let mut entities_components = WorldEntitiesComponents::new();
+
+        let entities = entities_components.spawn_batch((0..3).map(|i| (Health(i),)));
+        entities_components.add_component(entities[1], Stunned);
+
+        let snapshot = entities_components.snapshot().ok().unwrap();
+
+        entities_components.destroy_entity(entities[0]);
+        entities_components.remove_component::<Stunned>(entities[1]);
+        let spawned = entities_components.spawn((Health(3), Stunned));
+
+        // A system has seen every change made since the snapshot.
+        let last_run = entities_components.increment_change_tick();
+
+        entities_components.restore(&snapshot);
+
+        let ticks = RunTicks { last_run, this_run: entities_components.increment_change_tick() };
+
+        let mut changed = entities_components.query_filtered::<Entity, Changed<Health>>(ticks).iter().collect::<Vec<_>>();
+        changed.sort();
+
+        assert_eq!(changed, entities);
+        assert_eq!(entities_components.query_filtered::<Entity, Added<Health>>(ticks).iter().collect::<Vec<_>>(), [entities[0]]);
+        assert_eq!(entities_components.query_filtered::<Entity, Added<Stunned>>(ticks).iter().collect::<Vec<_>>(), [entities[1]]);
+        assert_eq!(entities_components.removed_components::<Health>(ticks).collect::<Vec<_>>(), [spawned]);
+        assert_eq!(entities_components.removed_components::<Stunned>(ticks).collect::<Vec<_>>(), [spawned]);
+    }
+
+    #[test]
+    fn disabled_entities_are_skipped_unless_included() {
+        let mut entities_components = WorldEntitiesComponents::new();
+
+        let entities = entities_components.spawn_batch((0..3).map(|i| (Position(i),)));
+
+        entities_components.add_component(entities[1], Disabled);
+
+        let positions = entities_components.query::<&Position>().iter().map(|p| p.0).collect::<Vec<_>>();
+        assert_eq!(positions, [0, 2]);
+        assert!(entities_components.query::<Entity>().get(entities[1]).is_none());
+        assert!(entities_components.is_disabled(entities[1]));
+
+        let ticks = RunTicks { last_run: Tick::new(0), this_run: entities_components.change_tick() };
+
+        assert_eq!(entities_components.query_filtered::<&Position, IncludeDisabled>(ticks).len(), 3);
+        assert_eq!(entities_components.query_filtered::<&Position, With<Disabled>>(ticks).get(entities[1]).unwrap().0, 1);
+
+        entities_components.remove_component::<Disabled>(entities[1]);
+
+        assert_eq!(entities_components.query::<&Position>().get(entities[1]).unwrap().0, 1);
+    }
+
+    #[test]
+    fn entity_bits_round_trip() {
+        let mut entities_components = WorldEntitiesComponents::new();
+
+        let first = entities_components.create_entity();
+        entities_components.destroy_entity(first);
+        let second = entities_components.create_entity();
+
+        assert_eq!(std::mem::size_of::<Entity>(), 8);
+        assert_eq!(second.to_bits(), 3 << 32);
+        assert_eq!(Entity::from_bits(second.to_bits()), second);
+        assert!(first < second);
+        assert_eq!(second.to_string(), "0v3");
+        assert!(!entities_components.contains_entity(Entity::from_bits(first.to_bits())));
+    }
+
+    #[test]
+    fn command_queue_applies_structural_changes_in_order() {
+        let mut entities_components = WorldEntitiesComponents::new();
+
+        let entity = entities_components.spawn((Position(0),));
+
+        let mut commands = CommandQueue::new();
+        let spawned = commands.spawn(&entities_components, (Position(1), Velocity(1)));
+        commands.add_component(spawned, Tag(1));
+        commands.insert_bundle(entity, (Velocity(2),));
+        commands.remove_component::<Position>(entity);
+
+        assert_eq!(entities_components.query::<&Velocity>().len(), 0);
+        assert!(!entities_components.contains_entity(spawned));
+
+        // Creating an entity directly creates the reserved one first, so their ids do not clash.
+        let created = entities_components.create_entity();
+        assert!(entities_components.contains_entity(spawned));
+        assert_ne!(created, spawned);
+
+        commands.apply(&mut entities_components);
+
+        assert!(commands.is_empty());
+        assert_eq!(entities_components.query::<(&Position, &Velocity)>().len(), 1);
+        assert_eq!(entities_components.get_component::<Position>(spawned).unwrap().0, 1);
+        assert_eq!(entities_components.get_component::<Tag>(spawned).unwrap().0, 1);
+        assert_eq!(entities_components.get_component::<Velocity>(entity).unwrap().0, 2);
+        assert!(entities_components.get_component::<Position>(entity).is_none());
     }
 }
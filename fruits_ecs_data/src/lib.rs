@@ -1,3 +1,3 @@
 mod world_data;
 
-pub use world_data::WorldData;
+pub use world_data::{WorldData, WorldSnapshot};
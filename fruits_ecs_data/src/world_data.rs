@@ -1,11 +1,17 @@
-use fruits_ecs_component::WorldEntitiesComponents;
-use fruits_ecs_resource::ResourcesHolder;
+use fruits_ecs_component::{EntitiesComponentsSnapshot, WorldEntitiesComponents};
+use fruits_ecs_resource::{ResourcesHolder, ResourcesSnapshot};
 
 pub struct WorldData {
     resources: ResourcesHolder,
     entities_components: WorldEntitiesComponents,
 }
 
+/// Entities, components and opted in resources of a world, restored by `WorldData::restore`.
+pub struct WorldSnapshot {
+    resources: ResourcesSnapshot,
+    entities_components: EntitiesComponentsSnapshot,
+}
+
 impl WorldData {
     pub fn new() -> Self {
         Self {
@@ -29,4 +35,17 @@ impl WorldData {
     pub fn entities_components_mut(&mut self) -> &mut WorldEntitiesComponents {
         &mut self.entities_components
     }
+
+    /// Returns the names of the stored components that cannot be cloned on failure.
+    pub fn snapshot(&self) -> Result<WorldSnapshot, Vec<&'static str>> {
+        Ok(WorldSnapshot {
+            entities_components: self.entities_components.snapshot()?,
+            resources: self.resources.snapshot(),
+        })
+    }
+
+    pub fn restore(&mut self, snapshot: &WorldSnapshot) {
+        self.entities_components.restore(&snapshot.entities_components);
+        self.resources.restore(&snapshot.resources);
+    }
 }
\ No newline at end of file
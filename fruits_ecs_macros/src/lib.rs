@@ -1,21 +1,60 @@
 use proc_macro::TokenStream;
 
-#[proc_macro_derive(Component)]
+/// Supports `#[component(sparse_set)]` attribute to store the component in a sparse set instead of archetype tables,
+/// `#[component(require(A, B))]` attribute to insert the default `A` and `B` together with the component,
+/// and `#[component(clone)]` attribute to clone the `Clone` component with its entity.
+/// The deriving crate should depend on `fruits_ecs_component`.
+#[proc_macro_derive(Component, attributes(component))]
 pub fn derive_component(stream: TokenStream) -> TokenStream {
+    let args = get_attribute_args(stream.clone(), "component");
+
+    let is_sparse_set = args.iter().any(|a| a == "sparse_set");
+    let is_clone = args.iter().any(|a| a == "clone");
+
+    let required = get_attribute_nested_args(stream.clone(), "component", "require");
+
     let Some(struct_name) = get_struct_name(stream) else {
         panic!("The name of the struct is not found.");
     };
 
-    format!("impl Component for {struct_name} {{ }}").parse().unwrap()
+    let storage_type = match is_sparse_set {
+        true => "const STORAGE_TYPE: ::fruits_ecs_component::StorageType = ::fruits_ecs_component::StorageType::SparseSet;",
+        false => "",
+    };
+
+    let clone = match is_clone {
+        true => "const CLONE: Option<::fruits_ecs_component::CloneFn> = Some(::fruits_ecs_component::clone_component::<Self>);",
+        false => "",
+    };
+
+    let register_required = match required.is_empty() {
+        true => String::new(),
+        false => format!(
+            "fn register_required(required: &mut ::fruits_ecs_component::RequiredComponents) {{ {} }}",
+            required.iter().map(|r| format!("required.require::<{r}>();")).collect::<String>(),
+        ),
+    };
+
+    format!("impl Component for {struct_name} {{ {storage_type} {clone} {register_required} }}").parse().unwrap()
 }
 
-#[proc_macro_derive(Resource)]
+/// Supports `#[resource(snapshot)]` attribute to clone the `Clone` resource into world snapshots.
+#[proc_macro_derive(Resource, attributes(resource))]
 pub fn derive_resource(stream: TokenStream) -> TokenStream {
+    let is_snapshot = get_attribute_args(stream.clone(), "resource")
+        .iter()
+        .any(|a| a == "snapshot");
+
     let Some(struct_name) = get_struct_name(stream) else {
         panic!("The name of the struct is not found.");
     };
 
-    format!("impl Resource for {struct_name} {{ }}").parse().unwrap()
+    let snapshot = match is_snapshot {
+        true => "const SNAPSHOT: Option<fn(&Self) -> Self> = Some(Self::clone);",
+        false => "",
+    };
+
+    format!("impl Resource for {struct_name} {{ {snapshot} }}").parse().unwrap()
 }
 
 #[proc_macro_derive(SystemResource)]
@@ -27,6 +66,133 @@ pub fn derive_system_resource(stream: TokenStream) -> TokenStream {
     format!("impl SystemResource for {struct_name} {{ }}").parse().unwrap()
 }
 
+const SYSTEM_PARAM: &str = "::fruits_ecs_system_params::derive_support::SystemParam";
+const SYSTEM_INPUT: &str = "::fruits_ecs_system_params::derive_support::SystemInput";
+const DATA_USAGE: &str = "::fruits_ecs_system_params::derive_support::DataUsage";
+
+/// Merges the fields of a struct with a single lifetime into one parameter: `struct Params<'w> { a: Res<'w, A> }`.
+/// Every field is built with `SystemParam::new`, the struct is not available if any of the fields is not.
+/// The deriving crate should depend on `fruits_ecs_system_params`.
+#[proc_macro_derive(SystemParam)]
+pub fn derive_system_param(stream: TokenStream) -> TokenStream {
+    let Some(struct_name) = get_struct_name(stream.clone()) else {
+        panic!("The name of the struct is not found.");
+    };
+
+    let Some((lifetime, fields)) = get_struct_lifetime_and_fields(stream) else {
+        panic!("SystemParam can only be derived for structs with named fields and a single lifetime. Struct: {struct_name}.");
+    };
+
+    let fill_data_usage = fields.iter()
+        .map(|(_, ty)| format!("<{ty} as {SYSTEM_PARAM}>::fill_data_usage(usage);"))
+        .collect::<String>();
+
+    let new = fields.iter()
+        .map(|(name, ty)| format!("{name}: <{ty} as {SYSTEM_PARAM}>::new(input)?,"))
+        .collect::<String>();
+
+    format!("
+        unsafe impl<{lifetime}> {SYSTEM_PARAM} for {struct_name}<{lifetime}> {{
+            type Item<'d> = {struct_name}<'d>;
+
+            fn fill_data_usage(usage: &mut {DATA_USAGE}) {{ {fill_data_usage} }}
+
+            fn new<'d>(input: {SYSTEM_INPUT}<'d>) -> Option<Self::Item<'d>> {{
+                Some({struct_name} {{ {new} }})
+            }}
+        }}
+    ").parse().unwrap()
+}
+
+/// Returns identifiers listed in all the `#[name(..)]` attributes.
+fn get_attribute_args(stream: TokenStream, name: &str) -> Vec<String> {
+    get_attribute_streams(stream, name)
+        .into_iter()
+        .flatten()
+        .filter_map(|arg| match arg {
+            proc_macro::TokenTree::Ident(arg) => Some(arg.to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Returns comma separated types listed in all the `#[name(nested(..))]` attributes.
+fn get_attribute_nested_args(stream: TokenStream, name: &str, nested: &str) -> Vec<String> {
+    let mut args = Vec::new();
+
+    for attribute_args in get_attribute_streams(stream, name) {
+        let mut iter = attribute_args.into_iter();
+
+        while let Some(tree) = iter.next() {
+            let proc_macro::TokenTree::Ident(ident) = tree else {
+                continue;
+            };
+
+            if ident.to_string() != nested {
+                continue;
+            }
+
+            let Some(proc_macro::TokenTree::Group(nested_args)) = iter.next() else {
+                continue;
+            };
+
+            let mut arg = String::new();
+
+            for tree in nested_args.stream() {
+                match tree {
+                    proc_macro::TokenTree::Punct(punct) if punct.as_char() == ',' => args.push(std::mem::take(&mut arg)),
+                    tree => arg.push_str(&tree.to_string()),
+                }
+            }
+
+            if !arg.is_empty() {
+                args.push(arg);
+            }
+        }
+    }
+
+    args
+}
+
+/// Returns the argument streams of all the `#[name(..)]` attributes.
+fn get_attribute_streams(stream: TokenStream, name: &str) -> Vec<TokenStream> {
+    let mut streams = Vec::new();
+
+    let mut iter = stream.into_iter().peekable();
+
+    while let Some(tree) = iter.next() {
+        let proc_macro::TokenTree::Punct(punct) = tree else {
+            continue;
+        };
+
+        if punct.as_char() != '#' {
+            continue;
+        }
+
+        let Some(proc_macro::TokenTree::Group(attribute)) = iter.peek() else {
+            continue;
+        };
+
+        let mut attribute_iter = attribute.stream().into_iter();
+
+        let Some(proc_macro::TokenTree::Ident(attribute_name)) = attribute_iter.next() else {
+            continue;
+        };
+
+        if attribute_name.to_string() != name {
+            continue;
+        }
+
+        let Some(proc_macro::TokenTree::Group(attribute_args)) = attribute_iter.next() else {
+            continue;
+        };
+
+        streams.push(attribute_args.stream());
+    }
+
+    streams
+}
+
 fn get_struct_name(stream: TokenStream) -> Option<String> {
     let mut iter = stream.into_iter();
 
@@ -48,3 +214,90 @@ fn get_struct_name(stream: TokenStream) -> Option<String> {
 
     Some(name_ident.to_string())
 }
+
+/// Returns the only lifetime of the struct and the names and types of its fields.
+fn get_struct_lifetime_and_fields(stream: TokenStream) -> Option<(String, Vec<(String, String)>)> {
+    let mut iter = stream.into_iter()
+        .skip_while(|t| !matches!(t, proc_macro::TokenTree::Ident(i) if i.to_string() == "struct"))
+        .skip(2)
+        .peekable();
+
+    let mut generics = Vec::new();
+
+    if matches!(iter.peek(), Some(proc_macro::TokenTree::Punct(p)) if p.as_char() == '<') {
+        iter.next();
+
+        for tree in iter.by_ref() {
+            if matches!(&tree, proc_macro::TokenTree::Punct(p) if p.as_char() == '>') {
+                break;
+            }
+
+            generics.push(tree);
+        }
+    }
+
+    let [proc_macro::TokenTree::Punct(quote), proc_macro::TokenTree::Ident(lifetime)] = generics.as_slice() else {
+        return None;
+    };
+
+    if quote.as_char() != '\'' {
+        return None;
+    }
+
+    let fields = iter.find_map(|tree| match tree {
+        proc_macro::TokenTree::Group(group) if group.delimiter() == proc_macro::Delimiter::Brace => Some(group.stream()),
+        _ => None,
+    })?;
+
+    Some((format!("'{lifetime}"), get_fields(fields)))
+}
+
+/// Splits named fields by the commas outside of the type generics, skipping attributes and visibility.
+fn get_fields(stream: TokenStream) -> Vec<(String, String)> {
+    let mut fields = Vec::new();
+
+    let mut iter = stream.into_iter().peekable();
+
+    loop {
+        let name = loop {
+            match iter.next() {
+                None => return fields,
+                Some(proc_macro::TokenTree::Punct(p)) if p.as_char() == '#' => { iter.next(); },
+                Some(proc_macro::TokenTree::Ident(i)) if i.to_string() == "pub" => {
+                    if matches!(iter.peek(), Some(proc_macro::TokenTree::Group(_))) {
+                        iter.next();
+                    }
+                },
+                Some(proc_macro::TokenTree::Ident(i)) => break i.to_string(),
+                Some(_) => { },
+            }
+        };
+
+        // The colon.
+        iter.next();
+
+        let mut ty = Vec::new();
+        let mut depth = 0;
+        let mut previous_char = ' ';
+
+        for tree in iter.by_ref() {
+            let char = match &tree {
+                proc_macro::TokenTree::Punct(p) => p.as_char(),
+                _ => ' ',
+            };
+
+            match char {
+                ',' if depth == 0 => break,
+                '<' => depth += 1,
+                '>' if previous_char != '-' => depth -= 1,
+                _ => { },
+            }
+
+            previous_char = char;
+
+            ty.push(tree);
+        }
+
+        fields.push((name, TokenStream::from_iter(ty).to_string()));
+    }
+}
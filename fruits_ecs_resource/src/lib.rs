@@ -2,4 +2,4 @@ mod resource;
 mod resources_holder;
 
 pub use resource::Resource;
-pub use resources_holder::ResourcesHolder;
\ No newline at end of file
+pub use resources_holder::{ResourcesHolder, ResourcesSnapshot};
\ No newline at end of file
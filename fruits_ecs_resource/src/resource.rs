@@ -1 +1,4 @@
-pub trait Resource : 'static + Send + Sync { }
\ No newline at end of file
+pub trait Resource : 'static + Send + Sync {
+    /// Clones the resource into world snapshots. `Some(Self::clone)` for `Clone` resources restored on rollback.
+    const SNAPSHOT: Option<fn(&Self) -> Self> = None;
+}
\ No newline at end of file
@@ -1,5 +1,6 @@
 use std::{
-    any::Any,
+    any::{Any, TypeId},
+    collections::HashMap,
     sync::{
         RwLock, RwLockReadGuard, RwLockWriteGuard
     }
@@ -9,19 +10,47 @@ use fruits_utils::typed_map::{strategies::SendSyncStrategy, TypedMap};
 
 use crate::resource::Resource;
 
+type SnapshotFn = fn(&ResourcesHolder) -> SnapshotResource;
+
+struct SnapshotResource {
+    resource: Box<dyn Any + Send + Sync>,
+    restore: fn(&mut ResourcesHolder, &(dyn Any + Send + Sync)),
+}
+
+/// Clones of the resources that opted in with `Resource::SNAPSHOT`.
+pub struct ResourcesSnapshot {
+    resources: Vec<SnapshotResource>,
+}
+
+impl ResourcesSnapshot {
+    pub fn len(&self) -> usize {
+        self.resources.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.resources.is_empty()
+    }
+}
+
 pub struct ResourcesHolder {
     resources: TypedMap<SendSyncStrategy>,
+    snapshot_fns: HashMap<TypeId, SnapshotFn>,
 }
 
 impl ResourcesHolder {
     pub fn new() -> Self {
         Self {
             resources: TypedMap::new(),
+            snapshot_fns: HashMap::new(),
         }
     }
 
     pub fn insert<R: Resource + Any>(&mut self, resource: R) {
         self.resources.insert(RwLock::new(resource));
+
+        if R::SNAPSHOT.is_some() {
+            self.snapshot_fns.insert(TypeId::of::<R>(), snapshot_resource::<R>);
+        }
     }
 
     pub fn get<R: Resource>(&self) -> Option<RwLockReadGuard<R>> {
@@ -31,4 +60,31 @@ impl ResourcesHolder {
     pub fn get_mut<R: Resource>(&self) -> Option<RwLockWriteGuard<R>> {
         self.resources.get_ref::<RwLock<R>>()?.try_write().ok()
     }
+
+    /// Clones the resources that opted in. Panics if any of them is locked for writing.
+    pub fn snapshot(&self) -> ResourcesSnapshot {
+        ResourcesSnapshot {
+            resources: self.snapshot_fns.values().map(|snapshot| snapshot(self)).collect(),
+        }
+    }
+
+    /// Replaces the resources with clones from the snapshot. Resources missing in the snapshot are kept.
+    pub fn restore(&mut self, snapshot: &ResourcesSnapshot) {
+        for resource in snapshot.resources.iter() {
+            (resource.restore)(self, resource.resource.as_ref());
+        }
+    }
+}
+
+fn snapshot_resource<R: Resource>(resources: &ResourcesHolder) -> SnapshotResource {
+    let resource = resources.get::<R>().expect("The resource is locked.");
+
+    SnapshotResource {
+        resource: Box::new((R::SNAPSHOT.unwrap())(&resource)),
+        restore: restore_resource::<R>,
+    }
+}
+
+fn restore_resource<R: Resource>(resources: &mut ResourcesHolder, resource: &(dyn Any + Send + Sync)) {
+    resources.insert((R::SNAPSHOT.unwrap())(resource.downcast_ref::<R>().unwrap()));
 }
\ No newline at end of file
@@ -1,5 +1,6 @@
-use std::{any::{Any, TypeId}, collections::{HashMap, HashSet}, sync::{Arc, Mutex, RwLock}};
+use std::{any::{Any, TypeId}, collections::{HashMap, HashSet}, panic::{self, AssertUnwindSafe}, sync::{atomic::{AtomicBool, Ordering}, Arc, Mutex, RwLock}};
 
+use fruits_ecs_component::{CommandQueue, Tick};
 use fruits_ecs_data::WorldData;
 use fruits_ecs_system::{SystemInput, SystemWithMarker};
 use fruits_utils::thread_pool::ThreadPool;
@@ -10,23 +11,51 @@ use crate::order_graph::OrderGraph;
 
 use super::system_order;
 
+/// What happens to a system whose parameters cannot be obtained, like a missing resource.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum UnavailableParamsPolicy {
+    /// Panics on the thread executing the schedule iteration.
+    #[default]
+    Panic,
+    /// Warns once and skips the system until its parameters are available again.
+    /// Component changes are still seen once the system runs, but removed components and events older than two iterations are lost.
+    SkipWithWarning,
+}
+
 pub struct ScheduleBehavior {
     systems: Arc<[Arc<dyn System>]>,
     system_datas: Arc<[Mutex<SystemResourcesHolder>]>,
+    /// Whether the previous run of the system was skipped, so the warning is not repeated every iteration.
+    skipped_systems: Arc<[AtomicBool]>,
     execution_graph: Arc<OrderGraph>,
-    thread_pool: ThreadPool,
+    thread_pool: Arc<ThreadPool>,
+    unavailable_params_policy: UnavailableParamsPolicy,
+    previous_iteration_tick: Mutex<Option<Tick>>,
 }
 
 impl ScheduleBehavior {
-    pub fn new(systems: Arc<[Arc<dyn System>]>, execution_graph: Arc<OrderGraph>) -> Self {
+    pub fn new(
+        systems: Arc<[Arc<dyn System>]>,
+        execution_graph: Arc<OrderGraph>,
+        thread_pool: Arc<ThreadPool>,
+        unavailable_params_policy: UnavailableParamsPolicy,
+    ) -> Self {
         Self {
             system_datas: systems.iter().map(|_| Mutex::new(SystemResourcesHolder::new())).collect::<Arc<_>>(),
+            skipped_systems: systems.iter().map(|_| AtomicBool::new(false)).collect::<Arc<_>>(),
             systems,
             execution_graph,
-            thread_pool: ThreadPool::new(Self::non_main_threads_count())
+            thread_pool,
+            unavailable_params_policy,
+            previous_iteration_tick: Mutex::new(None),
         }
     }
 
+    /// Systems and their parallel queries share the pool, so it is created once for all the schedules.
+    pub fn new_thread_pool() -> Arc<ThreadPool> {
+        Arc::new(ThreadPool::new(Self::non_main_threads_count()))
+    }
+
     fn non_main_threads_count() -> usize {
         match std::thread::available_parallelism() {
             Ok(count) => (count.get() - 1).max(1),
@@ -35,8 +64,13 @@ impl ScheduleBehavior {
     }
 
     pub fn execute_iteration(&self, data: &Arc<RwLock<WorldData>>) {
+        let iteration_tick = data.read().unwrap().entities_components().change_tick();
+
         let iter = Arc::new(Mutex::new(self.execution_graph.iter()));
 
+        // The first panic of a system, resumed once every started system has ended.
+        let system_panic = Arc::new(Mutex::new(None));
+
         loop {
             let system_index = {
                 let mut iter = iter.lock().unwrap();
@@ -56,18 +90,56 @@ impl ScheduleBehavior {
                 let iter = Arc::clone(&iter);
                 let systems = Arc::clone(&self.systems);
                 let system_datas = Arc::clone(&self.system_datas);
+                let skipped_systems = Arc::clone(&self.skipped_systems);
+                let thread_pool = Arc::clone(&self.thread_pool);
+                let unavailable_params_policy = self.unavailable_params_policy;
+                let system_panic = Arc::clone(&system_panic);
 
-                let job = move || {
+                let execute_system = move || {
                     let system = &systems[system_index];
                     let system_data = &system_datas[system_index];
 
+                    let system_data = &mut *system_data.try_lock().ok().unwrap();
+
+                    system_data.start_run(data.read().unwrap().entities_components().increment_change_tick());
+
                     let input = SystemInput {
                         world_data: &*data,
-                        system_data: &mut *system_data.try_lock().ok().unwrap(),
+                        system_data,
+                        thread_pool: &thread_pool,
                     };
     
-                    system.execute(input);
-                    
+                    match system.execute(input) {
+                        Ok(()) => {
+                            skipped_systems[system_index].store(false, Ordering::Relaxed);
+                            system_data.end_run();
+                        },
+                        // The last run tick is kept, so the component changes are still seen once the system runs.
+                        // Removed components and events are cleared every iteration regardless of skipped systems.
+                        Err(param) => match unavailable_params_policy {
+                            UnavailableParamsPolicy::Panic => panic!(
+                                "System cannot obtain its parameters. System: {}. Parameter: {}.",
+                                system.system_name(),
+                                param.param_name,
+                            ),
+                            UnavailableParamsPolicy::SkipWithWarning => {
+                                if !skipped_systems[system_index].swap(true, Ordering::Relaxed) {
+                                    log::warn!(
+                                        "System is skipped until it can obtain its parameters. System: {}. Parameter: {}.",
+                                        system.system_name(),
+                                        param.param_name,
+                                    );
+                                }
+                            },
+                        },
+                    }
+                };
+
+                let job = move || {
+                    if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(execute_system)) {
+                        system_panic.lock().unwrap().get_or_insert(payload);
+                    }
+
                     {
                         iter.lock().unwrap().end(system_index);
                     }
@@ -78,12 +150,49 @@ impl ScheduleBehavior {
                 self.thread_pool.push_job(Box::new(job));
             }
         }
+
+        let system_panic = system_panic.lock().unwrap().take();
+
+        if let Some(payload) = system_panic {
+            panic::resume_unwind(payload);
+        }
+
+        let mut data = data.write().unwrap();
+
+        self.apply_commands(&mut data);
+
+        // Every system has run since the previous iteration started, so older removals were seen by all of them.
+        let previous_iteration_tick = self.previous_iteration_tick.lock().unwrap().replace(iteration_tick);
+
+        if let Some(previous_iteration_tick) = previous_iteration_tick {
+            data.entities_components_mut().clear_removed_components(previous_iteration_tick);
+        }
+
+        if let Some(tick) = data.entities_components_mut().check_change_ticks() {
+            for system_data in self.system_datas.iter() {
+                system_data.lock().unwrap().check_change_tick(tick);
+            }
+        }
+    }
+
+    /// The sync point of the iteration: applies the `Commands` of every system in the systems order.
+    fn apply_commands(&self, data: &mut WorldData) {
+        for system_data in self.system_datas.iter() {
+            let system_data = system_data.lock().unwrap();
+
+            let Some(mut commands) = system_data.get::<CommandQueue>() else {
+                continue;
+            };
+
+            commands.apply(data.entities_components_mut());
+        }
     }
 }
 
 pub struct ScheduleBehaviorBuilder {
     systems: HashMap<TypeId, Arc<dyn System>>,
-    systems_ordering: HashSet<(TypeId, TypeId)>
+    systems_ordering: HashSet<(TypeId, TypeId)>,
+    unavailable_params_policy: UnavailableParamsPolicy,
 }
 
 impl ScheduleBehaviorBuilder {
@@ -91,9 +200,14 @@ impl ScheduleBehaviorBuilder {
         Self {
             systems: HashMap::new(),
             systems_ordering: HashSet::new(),
+            unavailable_params_policy: UnavailableParamsPolicy::default(),
         }
     }
 
+    pub fn set_unavailable_params_policy(&mut self, policy: UnavailableParamsPolicy) {
+        self.unavailable_params_policy = policy;
+    }
+
     pub fn add_system<M: 'static>(&mut self, system: impl SystemWithMarker<M> + Any) -> bool {
         self.systems.insert(system.type_id(), Arc::from(system.into_system_generic())).is_none()
     }
@@ -106,13 +220,13 @@ impl ScheduleBehaviorBuilder {
         self.systems_ordering.insert((previous_system.type_id(), next_system.type_id()));
     }
 
-    pub fn build(self) -> ScheduleBehavior {
+    pub fn build(self, thread_pool: Arc<ThreadPool>) -> ScheduleBehavior {
         let systems = system_order::sort_systems_by_order(&self.systems, &self.systems_ordering);
 
         let execution_graph = system_order::create_ordering_graph(&systems, &self.systems_ordering);
 
         let systems = systems.iter().map(|s| Arc::clone(&s.system)).collect::<Arc<_>>();
 
-        ScheduleBehavior::new(systems, Arc::new(execution_graph))
+        ScheduleBehavior::new(systems, Arc::new(execution_graph), thread_pool, self.unavailable_params_policy)
     }
 }
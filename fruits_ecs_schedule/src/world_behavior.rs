@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use super::schedule_behavior::{ScheduleBehavior, ScheduleBehaviorBuilder};
 
 #[derive(Clone, Copy)]
@@ -27,8 +29,10 @@ impl WorldBehaviorBuilder {
     }
 
     pub fn build(self) -> WorldBehavior {
+        let thread_pool = ScheduleBehavior::new_thread_pool();
+
         WorldBehavior {
-            schedule_behaviors: self.schedule_behaviors.map(|b| b.build()),
+            schedule_behaviors: self.schedule_behaviors.map(|b| b.build(Arc::clone(&thread_pool))),
         }
     }
 }
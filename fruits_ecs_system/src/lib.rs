@@ -7,6 +7,6 @@ mod system_input;
 
 pub use crate::system::System;
 pub use crate::system_with_marker::SystemWithMarker;
-pub use crate::system_param::SystemParam;
+pub use crate::system_param::{SystemParam, UnavailableParam};
 pub use crate::system_with_marker_adapter::SystemWithMarkerAdapter;
 pub use crate::system_input::SystemInput;
@@ -1,9 +1,10 @@
 use fruits_ecs_data_usage::DataUsage;
 
-use crate::system_input::SystemInput;
+use crate::{system_input::SystemInput, system_param::UnavailableParam};
 
 pub unsafe trait System : 'static + Send + Sync {
     fn fill_data_usage(&self, usage: &mut DataUsage);
-    fn execute<'d>(&self, data: SystemInput<'d>);
+    /// Does not run the system if any of its parameters cannot be obtained.
+    fn execute<'d>(&self, data: SystemInput<'d>) -> Result<(), UnavailableParam>;
     fn system_name(&self) -> &'static str;
 }
\ No newline at end of file
@@ -7,4 +7,63 @@ pub unsafe trait SystemParam {
 
     fn fill_data_usage(usage: &mut DataUsage);
     fn new<'d>(input: SystemInput<'d>) -> Option<Self::Item<'d>>;
-}
\ No newline at end of file
+}
+
+/// A parameter that cannot be obtained, the system does not run then.
+#[derive(Clone, Copy, Debug)]
+pub struct UnavailableParam {
+    pub param_name: &'static str,
+}
+
+/// `None` if the parameter cannot be obtained instead of skipping the system.
+unsafe impl<P: SystemParam> SystemParam for Option<P> {
+    type Item<'d> = Option<P::Item<'d>>;
+
+    fn fill_data_usage(usage: &mut DataUsage) {
+        P::fill_data_usage(usage);
+    }
+
+    fn new<'d>(input: SystemInput<'d>) -> Option<Self::Item<'d>> {
+        Some(P::new(input))
+    }
+}
+
+/// Every parameter of the tuple should be available.
+macro_rules! system_param_impl {
+    ($($P: ident),+) => {
+        unsafe impl<$($P),+> SystemParam for ($($P,)+)
+        where
+            $($P: SystemParam),+
+        {
+            type Item<'d> = (
+                $($P::Item<'d>,)+
+            );
+
+            fn fill_data_usage(usage: &mut DataUsage) {
+                $($P::fill_data_usage(usage));+;
+            }
+
+            fn new<'d>(input: SystemInput<'d>) -> Option<Self::Item<'d>> {
+                Some((
+                    $($P::new(input)?,)+
+                ))
+            }
+        }
+    };
+}
+
+system_param_impl!(P0);
+system_param_impl!(P0, P1);
+system_param_impl!(P0, P1, P2);
+system_param_impl!(P0, P1, P2, P3);
+system_param_impl!(P0, P1, P2, P3, P4);
+system_param_impl!(P0, P1, P2, P3, P4, P5);
+system_param_impl!(P0, P1, P2, P3, P4, P5, P6);
+system_param_impl!(P0, P1, P2, P3, P4, P5, P6, P7);
+system_param_impl!(P0, P1, P2, P3, P4, P5, P6, P7, P8);
+system_param_impl!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9);
+system_param_impl!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9, P10);
+system_param_impl!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9, P10, P11);
+system_param_impl!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9, P10, P11, P12);
+system_param_impl!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9, P10, P11, P12, P13);
+system_param_impl!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9, P10, P11, P12, P13, P14);
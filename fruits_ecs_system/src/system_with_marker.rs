@@ -1,10 +1,11 @@
 use fruits_ecs_data_usage::DataUsage;
 
-use crate::{system::System, system_input::SystemInput};
+use crate::{system::System, system_input::SystemInput, system_param::UnavailableParam};
 
 pub unsafe trait SystemWithMarker<M: 'static> : 'static + Send + Sync {
     fn fill_data_usage(&self, usage: &mut DataUsage);
-    fn execute<'d>(&self, data: SystemInput<'d>);
+    /// Does not run the system if any of its parameters cannot be obtained.
+    fn execute<'d>(&self, data: SystemInput<'d>) -> Result<(), UnavailableParam>;
     fn into_system_generic(self) -> Box<dyn System>;
     fn system_name(&self) -> &'static str;
 }
\ No newline at end of file
@@ -1,6 +1,6 @@
 use fruits_ecs_data_usage::DataUsage;
 
-use crate::{system::System, system_with_marker::SystemWithMarker, system_input::SystemInput};
+use crate::{system::System, system_with_marker::SystemWithMarker, system_input::SystemInput, system_param::UnavailableParam};
 
 pub struct SystemWithMarkerAdapter<M: 'static> {
     system_with_marker: Box<dyn SystemWithMarker<M>>,
@@ -20,7 +20,7 @@ where {
         self.system_with_marker.fill_data_usage(usage)
     }
 
-    fn execute<'d>(&self, data: SystemInput<'d>) {
+    fn execute<'d>(&self, data: SystemInput<'d>) -> Result<(), UnavailableParam> {
         self.system_with_marker.execute(data)
     }
 
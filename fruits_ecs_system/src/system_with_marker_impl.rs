@@ -3,7 +3,7 @@ use fruits_ecs_data_usage::DataUsage;
 use crate::{
     system::System,
     system_input::SystemInput,
-    system_param::SystemParam,
+    system_param::{SystemParam, UnavailableParam},
     system_with_marker::SystemWithMarker,
     system_with_marker_adapter::SystemWithMarkerAdapter,
 };
@@ -21,14 +21,14 @@ macro_rules! system_with_marker_impl {
                 $($P::fill_data_usage(_usage));*;
             }
         
-            fn execute<'d>(&self, _data: SystemInput<'d>) {
+            fn execute<'d>(&self, _data: SystemInput<'d>) -> Result<(), UnavailableParam> {
                 self(
-                    $($P::new(_data).unwrap_or_else(|| panic!(
-                        "System cannot obtain its parameters. System: {}. Parameter: {}.",
-                        std::any::type_name::<F>(),
-                        std::any::type_name::<$P>(),
-                    ))),*
+                    $($P::new(_data).ok_or(UnavailableParam {
+                        param_name: std::any::type_name::<$P>(),
+                    })?),*
                 );
+
+                Ok(())
             }
 
             fn into_system_generic(self) -> Box<dyn System> {
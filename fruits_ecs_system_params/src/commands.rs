@@ -0,0 +1,51 @@
+use std::{ops::{Deref, DerefMut}, sync::RwLock};
+
+use fruits_ecs_component::{Bundle, CommandQueue, Entity};
+use fruits_ecs_data::WorldData;
+use fruits_ecs_data_usage::*;
+use fruits_ecs_system::{SystemInput, SystemParam};
+use fruits_ecs_system_resource::SystemResourcesHolderGuard;
+
+/// Structural changes of the system, applied at the end of the schedule iteration.
+/// Takes no data locks, so the system can run in parallel with any other.
+pub struct Commands<'d> {
+    queue: SystemResourcesHolderGuard<'d, CommandQueue>,
+    world: &'d RwLock<WorldData>,
+}
+
+impl<'d> Commands<'d> {
+    /// Returns the reserved entity, which exists once the commands are applied.
+    /// Cannot be called by a system with exclusive world access.
+    pub fn spawn<B: Bundle + Send>(&mut self, bundle: B) -> Entity {
+        let world = self.world.try_read().expect("Entities cannot be reserved while the world is locked for writing.");
+
+        self.queue.spawn(world.entities_components(), bundle)
+    }
+}
+
+impl<'d> Deref for Commands<'d> {
+    type Target = CommandQueue;
+
+    fn deref(&self) -> &Self::Target {
+        &self.queue
+    }
+}
+
+impl<'d> DerefMut for Commands<'d> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.queue
+    }
+}
+
+unsafe impl<'a> SystemParam for Commands<'a> {
+    type Item<'d> = Commands<'d>;
+
+    fn fill_data_usage(_usage: &mut DataUsage) { }
+
+    fn new<'d>(input: SystemInput<'d>) -> Option<Self::Item<'d>> {
+        Some(Commands {
+            queue: input.system_data.get_or_create::<CommandQueue>()?,
+            world: input.world_data,
+        })
+    }
+}
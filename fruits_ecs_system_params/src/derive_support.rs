@@ -0,0 +1,84 @@
+pub use fruits_ecs_data_usage::DataUsage;
+pub use fruits_ecs_system::{SystemInput, SystemParam};
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, RwLock};
+
+    use fruits_ecs_data::WorldData;
+    use fruits_ecs_macros::SystemParam;
+    use fruits_ecs_resource::Resource;
+    use fruits_ecs_schedule::{ScheduleBehavior, ScheduleBehaviorBuilder};
+
+    use crate::{Res, ResMut};
+
+    struct Counter(u32);
+    impl Resource for Counter { }
+
+    struct Step(u32);
+    impl Resource for Step { }
+
+    struct Missing;
+    impl Resource for Missing { }
+
+    #[derive(SystemParam)]
+    struct StepParams<'w> {
+        counter: ResMut<'w, Counter>,
+        step: Option<Res<'w, Step>>,
+    }
+
+    fn add_step(mut params: StepParams) {
+        params.counter.0 += params.step.map_or(100, |s| s.0);
+    }
+
+    type CounterStep<'c, 's> = (ResMut<'c, Counter>, Res<'s, Step>);
+
+    fn add_nested_step(((mut counter, step), missing): (CounterStep, Option<Res<Missing>>)) {
+        assert!(missing.is_none());
+
+        counter.0 += step.0 * 10;
+    }
+
+    type EightSteps<'w> = (Res<'w, Step>, Res<'w, Step>, Res<'w, Step>, Res<'w, Step>, Res<'w, Step>, Res<'w, Step>, Res<'w, Step>, Res<'w, Step>);
+
+    fn count_sixteen_steps(first: EightSteps, second: EightSteps, mut counter: ResMut<Counter>) {
+        let (s0, s1, s2, s3, s4, s5, s6, s7) = first;
+        let (s8, s9, s10, s11, s12, s13, s14, s15) = second;
+
+        let steps = [s0.0, s1.0, s2.0, s3.0, s4.0, s5.0, s6.0, s7.0, s8.0, s9.0, s10.0, s11.0, s12.0, s13.0, s14.0, s15.0];
+
+        counter.0 += steps.iter().sum::<u32>() * 1000;
+    }
+
+    fn execute_with_step(system: impl FnOnce(&mut ScheduleBehaviorBuilder)) -> u32 {
+        let mut schedule = ScheduleBehaviorBuilder::new();
+        system(&mut schedule);
+
+        let mut data = WorldData::new();
+        data.resources_mut().insert(Counter(0));
+        data.resources_mut().insert(Step(1));
+
+        let data = Arc::new(RwLock::new(data));
+
+        schedule.build(ScheduleBehavior::new_thread_pool()).execute_iteration(&data);
+
+        let data = data.read().unwrap();
+        let counter = data.resources().get::<Counter>().unwrap().0;
+        counter
+    }
+
+    #[test]
+    fn derived_params_build_every_field() {
+        assert_eq!(execute_with_step(|s| { s.add_system(add_step); }), 1);
+    }
+
+    #[test]
+    fn nested_tuple_params_build_every_param() {
+        assert_eq!(execute_with_step(|s| { s.add_system(add_nested_step); }), 10);
+    }
+
+    #[test]
+    fn tuple_params_exceed_system_params_count() {
+        assert_eq!(execute_with_step(|s| { s.add_system(count_sixteen_steps); }), 16000);
+    }
+}
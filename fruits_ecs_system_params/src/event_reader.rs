@@ -0,0 +1,124 @@
+use std::marker::PhantomData;
+
+use fruits_ecs_data_usage::*;
+use fruits_ecs_system::{SystemInput, SystemParam};
+use fruits_ecs_system_resource::SystemResource;
+
+use crate::{Events, Local, Res};
+
+/// Id of the next event the system reads.
+pub struct EventCursor<T> {
+    next_id: usize,
+    _phantom: PhantomData<fn(T) -> T>,
+}
+
+impl<T> Default for EventCursor<T> {
+    fn default() -> Self {
+        Self {
+            next_id: 0,
+            _phantom: Default::default(),
+        }
+    }
+}
+
+impl<T: 'static> SystemResource for EventCursor<T> { }
+
+/// Reads the `T` events written since the previous run of the system.
+/// The cursor is local to the system, so readers of the same event type run in parallel.
+pub struct EventReader<'d, T: 'static + Send + Sync> {
+    events: Res<'d, Events<T>>,
+    cursor: Local<'d, EventCursor<T>>,
+}
+
+impl<'d, T: 'static + Send + Sync> EventReader<'d, T> {
+    pub fn read(&mut self) -> impl Iterator<Item = &T> {
+        let next_id = std::mem::replace(&mut self.cursor.next_id, self.events.next_id());
+
+        self.events.iter_from(next_id)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cursor.next_id >= self.events.next_id()
+    }
+}
+
+unsafe impl<'a, T: 'static + Send + Sync> SystemParam for EventReader<'a, T> {
+    type Item<'d> = EventReader<'d, T>;
+
+    /// The cursor is local to the system, so its usage is not added and readers do not order each other.
+    fn fill_data_usage(usage: &mut DataUsage) {
+        Res::<Events<T>>::fill_data_usage(usage);
+    }
+
+    fn new<'d>(input: SystemInput<'d>) -> Option<Self::Item<'d>> {
+        Some(EventReader {
+            events: Res::<Events<T>>::new(input)?,
+            cursor: Local::<EventCursor<T>>::new(input)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{atomic::{AtomicU32, Ordering}, Mutex};
+
+    use fruits_ecs_schedule::Schedule;
+    use fruits_ecs_world::WorldBuilder;
+
+    use super::*;
+    use crate::{add_event_to, EventWriter};
+
+    static WRITTEN_BATCHES: AtomicU32 = AtomicU32::new(0);
+    static FIRST_READ: Mutex<Vec<u32>> = Mutex::new(Vec::new());
+    static SECOND_READ: Mutex<Vec<u32>> = Mutex::new(Vec::new());
+
+    fn write_two_events(mut events: EventWriter<u32>) {
+        let batch = WRITTEN_BATCHES.fetch_add(1, Ordering::Relaxed);
+
+        if batch < 4 {
+            events.write_batch([batch * 2, batch * 2 + 1]);
+        }
+    }
+
+    fn read_first(mut events: EventReader<u32>) {
+        FIRST_READ.lock().unwrap().extend(events.read().copied());
+    }
+
+    fn read_second(mut events: EventReader<u32>) {
+        SECOND_READ.lock().unwrap().extend(events.read().copied());
+    }
+
+    #[test]
+    fn every_reader_sees_every_event_once() {
+        let mut world = WorldBuilder::new();
+
+        add_event_to::<u32>(&mut world);
+
+        let update = world.behavior_mut().get_mut(Schedule::Update);
+        update.add_system(write_two_events);
+        update.add_system(read_first);
+        update.add_system(read_second);
+
+        let world = world.build();
+
+        for _ in 0..6 {
+            world.execute_iteration(Schedule::Update);
+        }
+
+        assert_eq!(*FIRST_READ.lock().unwrap(), (0..8).collect::<Vec<_>>());
+        assert_eq!(*SECOND_READ.lock().unwrap(), (0..8).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn readers_of_the_same_events_do_not_conflict() {
+        let mut usage = DataUsage::new();
+
+        <(EventReader<u32>, EventReader<u32>) as SystemParam>::fill_data_usage(&mut usage);
+
+        let DataUsage::PerType(usage) = usage else {
+            panic!("Readers should not use the whole world.");
+        };
+
+        assert!(usage.values().values().all(|is_mutable| !is_mutable));
+    }
+}
@@ -1,9 +1,35 @@
-pub struct EventWriter<T> {
-    
+use fruits_ecs_data_usage::*;
+use fruits_ecs_system::{SystemInput, SystemParam};
+
+use crate::{Events, ResMut};
+
+/// Writes `T` events. Writers of the same event type run one after another.
+pub struct EventWriter<'d, T: 'static + Send + Sync> {
+    events: ResMut<'d, Events<T>>,
 }
 
-impl<T> EventWriter<T> {
+impl<'d, T: 'static + Send + Sync> EventWriter<'d, T> {
     pub fn write(&mut self, event: T) {
+        self.events.write(event);
+    }
+
+    pub fn write_batch(&mut self, events: impl IntoIterator<Item = T>) {
+        for event in events {
+            self.events.write(event);
+        }
+    }
+}
+
+unsafe impl<'a, T: 'static + Send + Sync> SystemParam for EventWriter<'a, T> {
+    type Item<'d> = EventWriter<'d, T>;
 
+    fn fill_data_usage(usage: &mut DataUsage) {
+        ResMut::<Events<T>>::fill_data_usage(usage);
     }
-}
\ No newline at end of file
+
+    fn new<'d>(input: SystemInput<'d>) -> Option<Self::Item<'d>> {
+        Some(EventWriter {
+            events: ResMut::<Events<T>>::new(input)?,
+        })
+    }
+}
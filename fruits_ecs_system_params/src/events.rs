@@ -0,0 +1,92 @@
+use fruits_ecs_resource::Resource;
+use fruits_ecs_schedule::Schedule;
+use fruits_ecs_world::WorldBuilder;
+
+use crate::ResMut;
+
+/// Double buffered events of the `T` type. Every event is kept for two buffer swaps,
+/// so each system running once per iteration sees it.
+pub struct Events<T> {
+    previous: Vec<T>,
+    current: Vec<T>,
+    /// Id of the first event in `previous`, ids grow with every written event.
+    previous_start: usize,
+}
+
+impl<T: 'static + Send + Sync> Resource for Events<T> { }
+
+impl<T> Events<T> {
+    pub fn new() -> Self {
+        Self {
+            previous: Vec::new(),
+            current: Vec::new(),
+            previous_start: 0,
+        }
+    }
+
+    pub fn write(&mut self, event: T) {
+        self.current.push(event);
+    }
+
+    /// Drops the events of the previous buffer and starts a new current one.
+    pub fn update(&mut self) {
+        self.previous_start += self.previous.len();
+        self.previous = std::mem::take(&mut self.current);
+    }
+
+    /// Id the next written event gets.
+    pub fn next_id(&self) -> usize {
+        self.previous_start + self.previous.len() + self.current.len()
+    }
+
+    /// Events with ids starting at `id`, without the dropped ones.
+    pub fn iter_from(&self, id: usize) -> impl Iterator<Item = &T> {
+        let skipped = id.saturating_sub(self.previous_start);
+
+        self.previous.iter().chain(self.current.iter()).skip(skipped)
+    }
+}
+
+impl<T> Default for Events<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Inserts the `Events<T>` resource and swaps its buffers once per update iteration.
+pub fn add_event_to<T: 'static + Send + Sync>(world: &mut WorldBuilder) {
+    if world.data().resources().get::<Events<T>>().is_none() {
+        world.data_mut().resources_mut().insert(Events::<T>::new());
+    }
+
+    world.behavior_mut().get_mut(Schedule::Update).add_system(update_events::<T>);
+}
+
+pub fn update_events<T: 'static + Send + Sync>(mut events: ResMut<Events<T>>) {
+    events.update();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_are_kept_for_two_updates() {
+        let mut events = Events::new();
+
+        events.write(0);
+        let cursor = events.next_id();
+        events.write(1);
+
+        events.update();
+        events.write(2);
+
+        assert_eq!(events.iter_from(0).copied().collect::<Vec<_>>(), [0, 1, 2]);
+        assert_eq!(events.iter_from(cursor).copied().collect::<Vec<_>>(), [1, 2]);
+
+        events.update();
+
+        assert_eq!(events.iter_from(0).copied().collect::<Vec<_>>(), [2]);
+        assert_eq!(events.next_id(), 3);
+    }
+}
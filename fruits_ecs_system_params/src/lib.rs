@@ -1,9 +1,18 @@
+// `derive(SystemParam)` refers to this crate by name, including in its own tests.
+extern crate self as fruits_ecs_system_params;
+
 mod exclusive_world_access;
 mod local;
 mod query;
 mod res;
 mod res_mut;
 mod entities_info;
+mod removed_components;
+mod commands;
+mod events;
+mod event_writer;
+mod event_reader;
+mod param_set;
 
 pub use exclusive_world_access::*;
 pub use local::*;
@@ -11,3 +20,13 @@ pub use query::*;
 pub use res::*;
 pub use res_mut::*;
 pub use entities_info::*;
+pub use removed_components::*;
+pub use commands::*;
+pub use events::*;
+pub use event_writer::*;
+pub use event_reader::*;
+pub use param_set::*;
+
+/// Paths of the code generated by `derive(SystemParam)`, so the deriving crates need no other dependencies.
+#[doc(hidden)]
+pub mod derive_support;
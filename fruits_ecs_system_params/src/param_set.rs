@@ -0,0 +1,101 @@
+use std::marker::PhantomData;
+
+use fruits_ecs_data_usage::*;
+use fruits_ecs_system::{SystemInput, SystemParam};
+
+/// Parameters with conflicting data usage, like a mutable and a readonly query of the same component.
+/// Declares the usage of all of them and hands out one at a time with `p0()`, `p1()` and so on.
+pub struct ParamSet<'d, T> {
+    input: SystemInput<'d>,
+    _phantom: PhantomData<fn() -> T>,
+}
+
+macro_rules! param_set_impl {
+    ($(($P: ident, $p: ident)),+) => {
+        unsafe impl<'a, $($P),+> SystemParam for ParamSet<'a, ($($P,)+)>
+        where
+            $($P: SystemParam + 'static),+
+        {
+            type Item<'d> = ParamSet<'d, ($($P,)+)>;
+
+            fn fill_data_usage(usage: &mut DataUsage) {
+                $($P::fill_data_usage(usage));+;
+            }
+
+            /// Every parameter is obtained once to check it is available.
+            fn new<'d>(input: SystemInput<'d>) -> Option<Self::Item<'d>> {
+                $(drop($P::new(input)?));+;
+
+                Some(ParamSet {
+                    input,
+                    _phantom: Default::default(),
+                })
+            }
+        }
+
+        impl<'d, $($P),+> ParamSet<'d, ($($P,)+)>
+        where
+            $($P: SystemParam),+
+        {
+            $(
+                pub fn $p(&mut self) -> $P::Item<'_> {
+                    $P::new(self.input).unwrap_or_else(|| panic!(
+                        "Parameter of the set cannot be obtained. Parameter: {}.",
+                        std::any::type_name::<$P>(),
+                    ))
+                }
+            )+
+        }
+    };
+}
+
+param_set_impl!((P0, p0));
+param_set_impl!((P0, p0), (P1, p1));
+param_set_impl!((P0, p0), (P1, p1), (P2, p2));
+param_set_impl!((P0, p0), (P1, p1), (P2, p2), (P3, p3));
+param_set_impl!((P0, p0), (P1, p1), (P2, p2), (P3, p3), (P4, p4));
+param_set_impl!((P0, p0), (P1, p1), (P2, p2), (P3, p3), (P4, p4), (P5, p5));
+param_set_impl!((P0, p0), (P1, p1), (P2, p2), (P3, p3), (P4, p4), (P5, p5), (P6, p6));
+param_set_impl!((P0, p0), (P1, p1), (P2, p2), (P3, p3), (P4, p4), (P5, p5), (P6, p6), (P7, p7));
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, RwLock};
+
+    use fruits_ecs_component::Component;
+    use fruits_ecs_data::WorldData;
+    use fruits_ecs_schedule::{ScheduleBehavior, ScheduleBehaviorBuilder};
+
+    use super::*;
+    use crate::WorldQuery;
+
+    struct Value(u32);
+    impl Component for Value { }
+
+    fn add_sum_to_values(mut values: ParamSet<(WorldQuery<&Value>, WorldQuery<&mut Value>)>) {
+        let sum = values.p0().iter().map(|v| v.0).sum::<u32>();
+
+        for value in values.p1().iter_mut() {
+            value.0 += sum;
+        }
+    }
+
+    #[test]
+    fn param_set_hands_out_conflicting_queries_one_at_a_time() {
+        let mut data = WorldData::new();
+
+        let entities = data.entities_components_mut().spawn_batch([(Value(1),), (Value(2),)]);
+
+        let mut schedule = ScheduleBehaviorBuilder::new();
+        schedule.add_system(add_sum_to_values);
+
+        let data = Arc::new(RwLock::new(data));
+
+        schedule.build(ScheduleBehavior::new_thread_pool()).execute_iteration(&data);
+
+        let data = data.read().unwrap();
+
+        assert_eq!(data.entities_components().get_component::<Value>(entities[0]).unwrap().0, 4);
+        assert_eq!(data.entities_components().get_component::<Value>(entities[1]).unwrap().0, 5);
+    }
+}
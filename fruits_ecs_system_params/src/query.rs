@@ -1,11 +1,11 @@
 use std::any::TypeId;
 
-use fruits_ecs_component::{ArchetypeIteratorItem, Component, Entity, WorldEntitiesComponentsQuery};
+use fruits_ecs_component::{ArchetypeIteratorItem, Component, Entity, QueryFilter, QueryParIter, QueryState, WorldEntitiesComponentsQuery};
 use fruits_ecs_data::WorldData;
 use fruits_ecs_data_usage::*;
 
 use fruits_ecs_system::{SystemInput, SystemParam};
-use fruits_utils::mapped_guard::{MappedGuard, RwLockReadGuarding};
+use fruits_utils::{mapped_guard::{MappedGuard, RwLockReadGuarding}, thread_pool::ThreadPool};
 
 pub unsafe trait WorldQueryIterParam {
     fn component_type() -> TypeId;
@@ -22,32 +22,37 @@ unsafe impl<P: Component> WorldQueryIterParam for &mut P {
     fn is_mutable() -> bool { true }
 }
 
-pub struct WorldQuery<'w, A: ArchetypeIteratorItem> {
-    query: MappedGuard::<'w, RwLockReadGuarding, WorldData, WorldEntitiesComponentsQuery<'w, A>>,
+pub struct WorldQuery<'w, A: ArchetypeIteratorItem, F: QueryFilter = ()> {
+    query: MappedGuard::<'w, RwLockReadGuarding, WorldData, WorldEntitiesComponentsQuery<'w, A, F>>,
+    thread_pool: &'w ThreadPool,
 }
 
 // todo: unsafe to sealed trait
-unsafe impl<'w, A: ArchetypeIteratorItem> SystemParam for WorldQuery<'w, A> {
-    type Item<'a> = WorldQuery<'a, A::Item<'a>>;
+unsafe impl<'w, A: ArchetypeIteratorItem, F: QueryFilter> SystemParam for WorldQuery<'w, A, F> {
+    type Item<'a> = WorldQuery<'a, A::Item<'a>, F>;
 
     fn fill_data_usage(usage: &mut DataUsage) {
         if let DataUsage::PerType(per_type) = usage {
             A::fill_usage(per_type);
+            F::fill_usage(per_type);
         }
     }
 
     fn new<'d>(input: SystemInput<'d>) -> Option<Self::Item<'d>> {
+        let mut state = input.system_data.get_or_create::<QueryState<<A::Item<'d> as ArchetypeIteratorItem>::Item<'static>, F>>()?;
+
         let guard = input.world_data.try_read().ok()?;
 
         let mapped_entities_components = MappedGuard::<'d, RwLockReadGuarding, WorldData, _>::map_from(guard, |w| w.entities_components());
 
         Some(Self::Item::<'d> {
-            query: mapped_entities_components.map_into(|e| e.query::<A::Item<'d>>()),
+            query: mapped_entities_components.map_into(|e| e.query_with_state::<A::Item<'d>, F>(&mut state, input.system_data.ticks())),
+            thread_pool: input.thread_pool,
         })
     }
 }
 
-impl<'w, A: ArchetypeIteratorItem> WorldQuery<'w, A> {
+impl<'w, A: ArchetypeIteratorItem, F: QueryFilter> WorldQuery<'w, A, F> {
     pub fn iter<'r>(&'r self) -> impl Iterator<Item = <A::ReadOnlyItem<'static> as ArchetypeIteratorItem>::Item<'w>> + 'r
         where 'w: 'r
     {
@@ -59,6 +64,25 @@ impl<'w, A: ArchetypeIteratorItem> WorldQuery<'w, A> {
         self.query.iter_mut()
     }
 
+    pub fn chunks<'r>(&'r self) -> impl Iterator<Item = <A::ReadOnlyItem<'static> as ArchetypeIteratorItem>::Slice<'w>> + 'r
+        where 'w: 'r
+    {
+        self.query.chunks()
+    }
+    pub fn chunks_mut<'r>(&'r mut self) -> impl Iterator<Item = <A::Item<'static> as ArchetypeIteratorItem>::Slice<'w>> + 'r
+        where 'w: 'r
+    {
+        self.query.chunks_mut()
+    }
+
+    /// Iterates on the thread pool shared with the schedule.
+    pub fn par_iter<'r>(&'r self) -> QueryParIter<'r, 'w, A::ReadOnlyItem<'static>, F> {
+        self.query.par_iter(self.thread_pool)
+    }
+    pub fn par_iter_mut<'r>(&'r mut self) -> QueryParIter<'r, 'w, A::Item<'static>, F> {
+        self.query.par_iter_mut(self.thread_pool)
+    }
+
     pub fn len(&self) -> usize {
         self.query.len()
     }
@@ -0,0 +1,40 @@
+use std::{marker::PhantomData, sync::RwLockReadGuard};
+
+use fruits_ecs_component::{Component, Entity, RunTicks};
+use fruits_ecs_data::WorldData;
+use fruits_ecs_data_usage::DataUsage;
+use fruits_ecs_system::{SystemInput, SystemParam};
+
+/// Entities that lost the `C` component since the previous run of the system, including destroyed ones.
+pub struct RemovedComponents<'d, C: Component> {
+    world: RwLockReadGuard<'d, WorldData>,
+    ticks: RunTicks,
+    _phantom: PhantomData<fn() -> C>,
+}
+
+impl<'d, C: Component> RemovedComponents<'d, C> {
+    pub fn iter(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.world.entities_components().removed_components::<C>(self.ticks)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+}
+
+unsafe impl<'b, C: Component> SystemParam for RemovedComponents<'b, C> {
+    type Item<'d> = RemovedComponents<'d, C>;
+
+    // The removal log is only written with exclusive world access.
+    fn fill_data_usage(_usage: &mut DataUsage) { }
+
+    fn new<'d>(input: SystemInput<'d>) -> Option<Self::Item<'d>> {
+        let guard = input.world_data.try_read().ok()?;
+
+        Some(RemovedComponents {
+            world: guard,
+            ticks: input.system_data.ticks(),
+            _phantom: PhantomData,
+        })
+    }
+}
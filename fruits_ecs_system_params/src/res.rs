@@ -39,3 +39,75 @@ unsafe impl<'a, R: Resource> SystemParam for Res<'a, R> {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{atomic::{AtomicU32, Ordering}, Arc, RwLock};
+
+    use fruits_ecs_schedule::{ScheduleBehavior, ScheduleBehaviorBuilder, UnavailableParamsPolicy};
+
+    use super::*;
+
+    struct Counter(u32);
+    impl Resource for Counter { }
+
+    static OPTIONAL_RUNS: AtomicU32 = AtomicU32::new(0);
+    static REQUIRED_RUNS: AtomicU32 = AtomicU32::new(0);
+
+    fn count_optional(counter: Option<Res<Counter>>) {
+        assert!(counter.is_none());
+
+        OPTIONAL_RUNS.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn count_required(counter: Res<Counter>) {
+        REQUIRED_RUNS.fetch_add(counter.0, Ordering::Relaxed);
+    }
+
+    fn read_counter(_counter: Res<Counter>) { }
+
+    #[test]
+    fn optional_res_is_none_without_resource() {
+        let mut schedule = ScheduleBehaviorBuilder::new();
+        schedule.add_system(count_optional);
+
+        let data = Arc::new(RwLock::new(WorldData::new()));
+
+        schedule.build(ScheduleBehavior::new_thread_pool()).execute_iteration(&data);
+
+        assert_eq!(OPTIONAL_RUNS.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn system_without_resource_is_skipped_until_it_exists() {
+        let mut schedule = ScheduleBehaviorBuilder::new();
+        schedule.set_unavailable_params_policy(UnavailableParamsPolicy::SkipWithWarning);
+        schedule.add_system(count_required);
+
+        let schedule = schedule.build(ScheduleBehavior::new_thread_pool());
+
+        let data = Arc::new(RwLock::new(WorldData::new()));
+
+        schedule.execute_iteration(&data);
+        schedule.execute_iteration(&data);
+
+        assert_eq!(REQUIRED_RUNS.load(Ordering::Relaxed), 0);
+
+        data.write().unwrap().resources_mut().insert(Counter(1));
+
+        schedule.execute_iteration(&data);
+
+        assert_eq!(REQUIRED_RUNS.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "System cannot obtain its parameters.")]
+    fn system_without_resource_panics_by_default() {
+        let mut schedule = ScheduleBehaviorBuilder::new();
+        schedule.add_system(read_counter);
+
+        let data = Arc::new(RwLock::new(WorldData::new()));
+
+        schedule.build(ScheduleBehavior::new_thread_pool()).execute_iteration(&data);
+    }
+}
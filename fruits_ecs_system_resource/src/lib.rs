@@ -1,20 +1,45 @@
 use std::{ops::{Deref, DerefMut}, sync::{Arc, Mutex, RwLock, RwLockWriteGuard}};
 
+use fruits_ecs_component::{ArchetypeIteratorItem, CommandQueue, QueryFilter, QueryState, RunTicks, Tick};
 use fruits_utils::typed_map::{strategies::SendStrategy, TypedMap};
 
 pub trait SystemResource : 'static + Send + Sync + Default { }
 
+impl<A: ArchetypeIteratorItem + 'static, F: QueryFilter> SystemResource for QueryState<A, F> { }
+
+impl SystemResource for CommandQueue { }
+
 pub struct SystemResourcesHolder {
     data: Mutex<TypedMap<SendStrategy>>,
+    ticks: RunTicks,
 }
 
 impl SystemResourcesHolder {
     pub fn new() -> Self {
         Self {
             data: Mutex::new(TypedMap::new()),
+            ticks: RunTicks::default(),
         }
     }
 
+    pub fn ticks(&self) -> RunTicks {
+        self.ticks
+    }
+
+    pub fn start_run(&mut self, this_run: Tick) {
+        self.ticks.this_run = this_run;
+    }
+
+    /// Changes made after this run become visible to the next one.
+    pub fn end_run(&mut self) {
+        self.ticks.last_run = self.ticks.this_run;
+    }
+
+    /// Clamps the last run tick with `Tick::check_tick`.
+    pub fn check_change_tick(&mut self, tick: Tick) {
+        self.ticks.last_run.check_tick(tick);
+    }
+
     pub fn get_or_create<S: SystemResource>(&self) -> Option<SystemResourcesHolderGuard<S>> {
         let data = &mut self.data.lock().unwrap();
         
@@ -26,6 +51,13 @@ impl SystemResourcesHolder {
 
         SystemResourcesHolderGuard::new(Arc::clone(state))
     }
+
+    /// Does not create the resource if the system has never used it.
+    pub fn get<S: SystemResource>(&self) -> Option<SystemResourcesHolderGuard<'_, S>> {
+        let data = self.data.lock().unwrap();
+
+        SystemResourcesHolderGuard::new(Arc::clone(data.get_ref::<Arc<RwLock<S>>>()?))
+    }
 }
 
 pub struct SystemResourcesHolderGuard<'a, S: SystemResource> {
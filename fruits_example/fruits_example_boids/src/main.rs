@@ -1,7 +1,7 @@
 use std::time::Instant;
 
 use fruits_prelude::*;
-use fruits_math::{Matrix, Matrix3x3, Vec3};
+use fruits_math::{Matrix, Vec3};
 use fruits_modules::{
     asset::AssetStorageResource,
     render::*,
@@ -42,21 +42,25 @@ fn main() {
     app.run();
 }
 
-#[derive(Component)]
+#[derive(Component, Clone)]
+#[component(clone)]
 struct Boid {
     target_direction: Vec3<f32>,
 }
 
-#[derive(Component)]
+#[derive(Component, Clone)]
+#[component(clone)]
 struct Velocity(pub Vec3<f32>);
 
-#[derive(Component)]
+#[derive(Component, Clone)]
+#[component(clone)]
 struct Motor {
     pub acceleration_direction: Vec3<f32>,
     pub strength: f32,
 }
 
-#[derive(Component)]
+#[derive(Component, Clone)]
+#[component(clone)]
 struct BoidTarget { }
 
 #[derive(Resource)]
@@ -112,36 +116,37 @@ fn init(mut world: ExclusiveWorldAccess) {
     let material = world.resources_mut().get_mut::<AssetStorageResource::<Material>>().unwrap().insert(material);
     let mesh = world.resources_mut().get_mut::<AssetStorageResource::<Mesh>>().unwrap().insert(mesh);
     
-    for _ in 0..100 {
-        let entity = world.entities_components_mut().create_entity();
-
-        world.entities_components_mut().add_component(entity, RenderMeshComponent { mesh: mesh.clone() });
-        world.entities_components_mut().add_component(entity, RenderMaterialComponent { material: material.clone() });
-        world.entities_components_mut().add_component(entity, Boid { target_direction: Vec3::with_all(0.0) });
-        world.entities_components_mut().add_component(entity, BoidTarget { });
-        world.entities_components_mut().add_component(entity, Motor { acceleration_direction: Vec3::with_all(0.0), strength: 0.01 });
-        world.entities_components_mut().add_component(entity, Velocity(Vec3::with_all(0.0)));
-        world.entities_components_mut().add_component(entity, GlobalTransform {
-            scale_rotation: Matrix3x3::IDENTITY,
-            position: Vec3::new(rand::random::<f32>(), rand::random::<f32>(), 0.0),
-        });
-
-        
+    let entities_components = world.entities_components_mut();
+
+    let boid = entities_components.spawn((
+        RenderMeshComponent { mesh },
+        RenderMaterialComponent { material },
+        Boid { target_direction: Vec3::with_all(0.0) },
+        BoidTarget { },
+        Motor { acceleration_direction: Vec3::with_all(0.0), strength: 0.01 },
+        Velocity(Vec3::with_all(0.0)),
+        GlobalTransform::IDENTITY,
+    ));
+
+    let boids = std::iter::once(boid)
+        .chain((1..100).map(|_| entities_components.clone_entity(boid).unwrap()))
+        .collect::<Vec<_>>();
+
+    for boid in boids {
+        entities_components.get_component_mut::<GlobalTransform>(boid).unwrap().position = Vec3::new(rand::random::<f32>(), rand::random::<f32>(), 0.0);
     }
 
-    {
-        let camera_entity = world.entities_components_mut().create_entity();
-
-        world.entities_components_mut().add_component(camera_entity, GlobalTransform {
+    world.entities_components_mut().spawn((
+        GlobalTransform {
             scale_rotation: Matrix::IDENTITY,
             position: Vec3::new(0.0_f32, 0.0_f32, -5.0f32),
-        });
-        world.entities_components_mut().add_component(camera_entity, CameraComponent {
+        },
+        CameraComponent {
             near: 0.1_f32,
             far: 1_000_f32,
             fov: 90_f32.to_radians(),
-        });
-    }
+        },
+    ));
 }
 
 fn accumulate_boid_separation(
@@ -151,7 +156,7 @@ fn accumulate_boid_separation(
 ) {
     let timer = Instant::now();
 
-    for (boid_entity, boid_transform, boid) in boids_queue.iter_mut() {
+    boids_queue.par_iter_mut().for_each(|(boid_entity, boid_transform, boid)| {
         let mut sum = Vec3::with_all(0.0_f32);
 
         for (target_entity, target_transform, _) in targets_queue.iter() {
@@ -169,7 +174,7 @@ fn accumulate_boid_separation(
         }
 
         boid.target_direction += sum.normalized_or_0();
-    }
+    });
 
     println!("{:>5} fps - {:>10.3} ms", (1.0 / timer.elapsed().as_secs_f64()) as u32, timer.elapsed().as_secs_f64() * 1000.0);
 }
@@ -194,16 +199,20 @@ fn apply_damping(
     mut query: WorldQuery<&mut Velocity>,
     boid_settings: Res<BoidSettings>,
 ) {
-    for velocity in query.iter_mut() {
-        velocity.0 -= velocity.0 * boid_settings.damping_factor;
+    for velocities in query.chunks_mut() {
+        for velocity in velocities {
+            velocity.0 -= velocity.0 * boid_settings.damping_factor;
+        }
     }
 }
 
 fn apply_velocity(
     mut query: WorldQuery<(&Velocity, &mut GlobalTransform)>
 ) {
-    for (velocity, transform) in query.iter_mut() {
-        transform.position += velocity.0;
+    for (velocities, transforms) in query.chunks_mut() {
+        for (velocity, transform) in velocities.iter().zip(transforms) {
+            transform.position += velocity.0;
+        }
     }
 }
 
@@ -26,17 +26,17 @@ fn run_ecs_behavior_integration_test() {
     world.behavior_mut().get_mut(Schedule::Start).order_systems(init_resources, init_mesh_material);
     world.behavior_mut().get_mut(Schedule::Start).order_systems(create_camera_uniform_bind_group_layout, init_mesh_material);
 
-    let entity = world.data_mut().entities_components_mut().create_entity();
-
-    world.data_mut().entities_components_mut().add_component(entity, GlobalTransform {
-        scale_rotation: Matrix::IDENTITY,
-        position: Vec3::new(0.0_f32, 0.0_f32, -1.0f32),
-    });
-    world.data_mut().entities_components_mut().add_component(entity, CameraComponent {
-        near: 0.1_f32,
-        far: 1_000_f32,
-        fov: 90_f32.to_radians(),
-    });
+    world.data_mut().entities_components_mut().spawn((
+        GlobalTransform {
+            scale_rotation: Matrix::IDENTITY,
+            position: Vec3::new(0.0_f32, 0.0_f32, -1.0f32),
+        },
+        CameraComponent {
+            near: 0.1_f32,
+            far: 1_000_f32,
+            fov: 90_f32.to_radians(),
+        },
+    ));
 
     dbg!(world.data_mut().entities_components_mut().entities_count());
 
@@ -49,9 +49,11 @@ fn run_ecs_behavior_integration_test() {
 struct SampleResource {}
 
 #[derive(Component)]
+#[component(sparse_set)]
 struct MovingCubeComponent;
 
 #[derive(Component)]
+#[component(sparse_set)]
 struct RotatingCubeComponent;
 
 #[derive(Resource)]
@@ -144,14 +146,14 @@ fn init_mesh_material(mut world: ExclusiveWorldAccess) {
         let parent = world.entities_components_mut().create_entity();
         world.entities_components_mut().add_component(parent, parent_transform);
 
-        let entity = world.entities_components_mut().create_entity();
-
-        world.entities_components_mut().add_component(entity, RenderMeshComponent { mesh: mesh.clone() });
-        world.entities_components_mut().add_component(entity, RenderMaterialComponent { material: material.clone() });
-        world.entities_components_mut().add_component(entity, LocalTransform::IDENTITY);
-        world.entities_components_mut().add_component(entity, ChildComponent { parent });
-        world.entities_components_mut().add_component(entity, RotatingCubeComponent);
-        world.entities_components_mut().add_component(entity, MovingCubeComponent);
+        world.entities_components_mut().spawn((
+            RenderMeshComponent { mesh: mesh.clone() },
+            RenderMaterialComponent { material: material.clone() },
+            LocalTransform::IDENTITY,
+            ChildComponent { parent },
+            RotatingCubeComponent,
+            MovingCubeComponent,
+        ));
     }
 }
 
@@ -1,11 +1,13 @@
 mod assets;
 mod components;
+mod params;
 mod resources;
 mod systems;
 
 pub use self::{
     assets::*,
     components::*,
+    params::*,
     resources::*,
     systems::*,
 };
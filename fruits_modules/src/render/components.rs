@@ -1,16 +1,18 @@
 use fruits_ecs_component::Component;
 use fruits_ecs_macros::Component;
 
-use crate::asset::AssetHandle;
+use crate::{asset::AssetHandle, transform::GlobalTransform};
 
 use super::assets::{Material, Mesh};
 
-#[derive(Component)]
+#[derive(Component, Clone)]
+#[component(clone, require(GlobalTransform))]
 pub struct RenderMeshComponent {
     pub mesh: AssetHandle<Mesh>,
 }
 
-#[derive(Component)]
+#[derive(Component, Clone)]
+#[component(clone)]
 pub struct RenderMaterialComponent {
     pub material: AssetHandle<Material>,
 }
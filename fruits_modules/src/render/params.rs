@@ -0,0 +1,23 @@
+use fruits_app::RenderStateResource;
+use fruits_ecs_macros::SystemParam;
+use fruits_ecs_system_params::{Res, ResMut};
+
+use crate::asset::AssetStorageResource;
+
+use super::{assets::{Material, Mesh}, resources::{CameraUniformBufferResource, InstanceBufferResource, SurfaceTextureResource}};
+
+/// Resources every pass drawing into the surface texture needs.
+#[derive(SystemParam)]
+pub struct RenderTargetParams<'w> {
+    pub render_state: Res<'w, RenderStateResource>,
+    pub camera_buffer: Res<'w, CameraUniformBufferResource>,
+    pub instance_buffer: Res<'w, InstanceBufferResource>,
+    pub surface_texture: ResMut<'w, SurfaceTextureResource>,
+}
+
+/// `None` until the asset storages are created.
+#[derive(SystemParam)]
+pub struct MeshAssetsParams<'w> {
+    pub meshes: Option<Res<'w, AssetStorageResource<Mesh>>>,
+    pub materials: Option<Res<'w, AssetStorageResource<Material>>>,
+}
@@ -3,9 +3,9 @@ use fruits_ecs_system_params::{ExclusiveWorldAccess, Res, ResMut, WorldQuery};
 use fruits_math::{Matrix, Matrix4x4};
 use wgpu::{util::{BufferInitDescriptor, DeviceExt}, BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingType, BufferBindingType, BufferUsages, CommandEncoderDescriptor, IndexFormat, LoadOp, Operations, RenderPassColorAttachment, RenderPassDescriptor, ShaderStages, StoreOp, TextureViewDescriptor};
 
-use crate::{asset::AssetStorageResource, transform::GlobalTransform};
+use crate::transform::GlobalTransform;
 
-use super::{assets::{Material, Mesh}, components::{CameraComponent, RenderMaterialComponent, RenderMeshComponent}, resources::{CameraUniformBufferGroupLayoutResource, CameraUniformBufferResource, InstanceBufferResource, SurfaceTextureResource}};
+use super::{components::{CameraComponent, RenderMaterialComponent, RenderMeshComponent}, params::{MeshAssetsParams, RenderTargetParams}, resources::{CameraUniformBufferGroupLayoutResource, CameraUniformBufferResource, InstanceBufferResource, SurfaceTextureResource}};
 
 pub fn create_camera_uniform_bind_group_layout(
     mut world: ExclusiveWorldAccess,
@@ -128,22 +128,23 @@ pub fn present_surface(mut surface_texture: ResMut<SurfaceTextureResource>) {
 
 pub fn render_meshes_and_materials(
     query: WorldQuery<(&GlobalTransform, &RenderMeshComponent, &RenderMaterialComponent)>,
-    render_state: Res<RenderStateResource>,
-    camera_buffer: Res<CameraUniformBufferResource>,
-    instance_buffer: Res<InstanceBufferResource>,
-    surface_texture: ResMut<SurfaceTextureResource>,
-    meshes: Res<AssetStorageResource<Mesh>>,
-    materials: Res<AssetStorageResource<Material>>,
+    target: RenderTargetParams,
+    assets: MeshAssetsParams,
 ) {
+    // Idles until the asset storages are created.
+    let (Some(meshes), Some(materials)) = (assets.meshes, assets.materials) else { return; };
+
     if query.len() == 0 {
         return;
     }
 
-    let Some(surface_texture) = &surface_texture.texture else { return; }; 
+    let Some(surface_texture) = &target.surface_texture.texture else { return; }; 
 
     let view = surface_texture.texture.create_view(&TextureViewDescriptor::default());
 
-    let render_state = &*render_state;
+    let render_state = &*target.render_state;
+    let camera_buffer = &*target.camera_buffer;
+    let instance_buffer = &*target.instance_buffer;
 
     for (transform, render_mesh, render_material) in query.iter() {
         let Some(mesh) = meshes.get(&render_mesh.mesh) else { continue; };
@@ -1,8 +1,8 @@
-use fruits_ecs_component::{Component, Entity};
+use fruits_ecs_component::{clone_component, CloneFn, CommandQueue, Component, Entity, RequiredComponents};
 use fruits_ecs_macros::Component;
 use fruits_math::{Matrix, Matrix3x3, Quat, Vec3};
 
-#[derive(Component, Copy, Clone)]
+#[derive(Copy, Clone)]
 pub struct GlobalTransform {
     pub position: Vec3<f32>,
     pub scale_rotation: Matrix3x3<f32>,
@@ -15,7 +15,21 @@ impl GlobalTransform {
     };
 }
 
-#[derive(Component, Copy, Clone)]
+impl Default for GlobalTransform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Component for GlobalTransform {
+    const CLONE: Option<CloneFn> = Some(clone_component::<Self>);
+
+    fn on_remove(entity: Entity, commands: &mut CommandQueue) {
+        commands.remove_component::<ParentComponent>(entity);
+    }
+}
+
+#[derive(Copy, Clone)]
 pub struct LocalTransform {
     pub position: Vec3<f32>,
     pub rotation: Quat<f32>,
@@ -30,12 +44,36 @@ impl LocalTransform {
     };
 }
 
+/// Entities with a local transform get the global one and can become children.
+impl Component for LocalTransform {
+    const CLONE: Option<CloneFn> = Some(clone_component::<Self>);
+
+    fn register_required(required: &mut RequiredComponents) {
+        required.require::<GlobalTransform>();
+        required.require::<ChildComponent>();
+    }
+
+    fn on_remove(entity: Entity, commands: &mut CommandQueue) {
+        commands.remove_component::<ChildComponent>(entity);
+    }
+}
+
 #[derive(Component, Clone)]
+#[component(clone)]
 pub struct ParentComponent {
     pub children: Vec<Entity>,
 }
 
 #[derive(Component, Copy, Clone)]
+#[component(clone)]
 pub struct ChildComponent {
     pub parent: Entity,
 }
+
+impl Default for ChildComponent {
+    fn default() -> Self {
+        Self {
+            parent: Entity::EMPTY,
+        }
+    }
+}
@@ -1,70 +1,10 @@
 use std::collections::VecDeque;
 
-use fruits_ecs_component::Entity;
-use fruits_ecs_system_params::{ExclusiveWorldAccess, WorldQuery};
+use fruits_ecs_component::{Entity, IncludeDisabled};
+use fruits_ecs_system_params::{Commands, ParamSet, WorldQuery};
 
 use super::{ChildComponent, GlobalTransform, LocalTransform, ParentComponent};
 
-pub fn adjust_component_sets(
-    mut world: ExclusiveWorldAccess,
-) {
-    #[derive(Clone, Copy)]
-    enum Change {
-        ParentAdd,
-        ParentRemove,
-        ChildAdd,
-        ChildRemove,
-        GlobalAdd,
-    }
-
-    let mut changes = Vec::new();
-
-    let entities_components = world.entities_components();
-    for e in entities_components.query::<Entity>().iter() {
-        // +-Parent +-Child +Global
-
-        // - -> -Parent -Child
-        // Local -> +Global +Child +Parent
-        // Global -> +Parent -Child
-        // Local, Global -> +Child +Parent
-        let contains_global = entities_components.get_component::<GlobalTransform>(e).is_some();
-        let contains_local = entities_components.get_component::<LocalTransform>(e).is_some();
-        let contains_child = entities_components.get_component::<ChildComponent>(e).is_some();
-        let contains_parent = entities_components.get_component::<ParentComponent>(e).is_some();
-
-        match (contains_global, contains_local) {
-            (false, false) => {
-                if contains_parent { changes.push((e, Change::ParentRemove)) };
-                if contains_child { changes.push((e, Change::ChildRemove)) };
-            },
-            (false, true) => {
-                changes.push((e, Change::GlobalAdd));
-                if !contains_parent { changes.push((e, Change::ParentAdd)) };
-                if !contains_child { changes.push((e, Change::ChildAdd)) };
-            },
-            (true, false) => {
-                if !contains_parent { changes.push((e, Change::ParentAdd)) };
-                if contains_child { changes.push((e, Change::ChildRemove)) };
-            },
-            (true, true) => {
-                if !contains_parent { changes.push((e, Change::ParentAdd)) };
-                if !contains_child { changes.push((e, Change::ChildAdd)) };
-            },
-        }
-    }
-
-    let entities_components = world.entities_components_mut();
-    for (e, change) in changes {
-        match change {
-            Change::ChildAdd => { entities_components.add_component(e, ChildComponent { parent: Entity::EMPTY }); },
-            Change::ChildRemove => { entities_components.remove_component::<ChildComponent>(e); },
-            Change::ParentAdd => { entities_components.add_component(e, ParentComponent { children: Vec::new() }); },
-            Change::ParentRemove => { entities_components.remove_component::<ParentComponent>(e); },
-            Change::GlobalAdd => { entities_components.add_component(e, GlobalTransform::IDENTITY); },
-        }
-    }
-}
-
 // - Update ParentComponents according to ChildComponents
 //     - Remove children from parent components
 //     - Add missing children to parent components with creation if needed
@@ -73,9 +13,10 @@ pub fn adjust_component_sets(
 
 // - Update ParentComponents according to ChildComponents
 //     - Remove children from parent components
+//     - Disabled children keep their place in the parent
 pub fn update_parents_remove_invalid_children(
-    mut parents: WorldQuery<(Entity, &mut ParentComponent)>,
-    children: WorldQuery<&ChildComponent>,
+    mut parents: WorldQuery<(Entity, &mut ParentComponent), IncludeDisabled>,
+    children: WorldQuery<&ChildComponent, IncludeDisabled>,
 ) {
     let mut indices_to_remove = Vec::new();
     
@@ -97,22 +38,32 @@ pub fn update_parents_remove_invalid_children(
 // - Update ParentComponents according to ChildComponents
 //     - Add missing children to parent components with creation if needed
 pub fn update_parents_add_missing_children(
-    mut world: ExclusiveWorldAccess,
+    children: WorldQuery<(Entity, &ChildComponent)>,
+    parents: WorldQuery<(&GlobalTransform, Option<&ParentComponent>), IncludeDisabled>,
+    mut commands: Commands,
 ) {
-    let children = world
-        .entities_components()
-        .query::<(Entity, &ChildComponent)>()
-        .iter()
-        .map(|(e, c)| (e, c.parent))
-        .filter(|(_, pe)| world.entities_components().get_component::<ParentComponent>(*pe).is_some())
-        .collect::<Vec<_>>();
+    for (child_entity, child) in children.iter() {
+        let parent_entity = child.parent;
 
-    for (child_entity, parent_entity) in children.into_iter() {
-        let parent = world.entities_components_mut().get_component_mut::<ParentComponent>(parent_entity).unwrap();
+        let Some((_, parent)) = parents.get(parent_entity) else {
+            continue;
+        };
 
-        if !parent.children.contains(&child_entity) {
-            parent.children.push(child_entity);
+        if parent.is_some_and(|p| p.children.contains(&child_entity)) {
+            continue;
         }
+
+        commands.push(move |w| {
+            w.add_component(parent_entity, ParentComponent { children: Vec::new() });
+
+            let Some(parent) = w.get_component_mut::<ParentComponent>(parent_entity) else {
+                return;
+            };
+
+            if !parent.children.contains(&child_entity) {
+                parent.children.push(child_entity);
+            }
+        });
     }
 }
 
@@ -120,44 +71,53 @@ pub fn update_parents_add_missing_children(
 // - Update ParentComponents according to ChildComponents
 //     - Destroy existing empty parent components
 pub fn update_parents_destroy_empty_parents(
-    mut world: ExclusiveWorldAccess,
+    parents: WorldQuery<(Entity, &ParentComponent)>,
+    mut commands: Commands,
 ) {
-    let empty_parents = world
-        .entities_components()
-        .query::<(Entity, &ParentComponent)>()
-        .iter()
-        .filter(|(_, p)| p.children.len() == 0)
-        .map(|(e, _)| e)
-        .collect::<Vec<_>>();
-
-    for parent in empty_parents {
-        world.entities_components_mut().remove_component::<ParentComponent>(parent);
+    for (parent_entity, _) in parents.iter().filter(|(_, p)| p.children.is_empty()) {
+        // Children added earlier in the same sync point keep the component.
+        commands.push(move |w| {
+            if w.get_component::<ParentComponent>(parent_entity).is_some_and(|p| p.children.is_empty()) {
+                w.remove_component::<ParentComponent>(parent_entity);
+            }
+        });
     }
 }
 
+/// Roots are collected with the readonly query before the mutable one updates the transforms.
+type GlobalTransformQueries<'s, 'r, 'w> = ParamSet<'s, (WorldQuery<'r, (Entity, &'r GlobalTransform)>, WorldQuery<'w, &'w mut GlobalTransform>)>;
+
 // - Calculate GlobalTransform from LocalTransform and child-parent relation with tree-ordering from a root parent to all the child leaves.
+//     - Disabled entities and their children keep the last calculated GlobalTransform
+//     - Children are added to ParentComponents by commands at the end of the iteration, so new children are calculated one iteration later
 pub fn calculate_global_transform(
-    mut world: ExclusiveWorldAccess,
+    hierarchy: WorldQuery<(Option<&LocalTransform>, Option<&ChildComponent>, Option<&ParentComponent>)>,
+    entities: WorldQuery<Entity, IncludeDisabled>,
+    mut transforms: GlobalTransformQueries,
 ) {
-    let entities_components = world.entities_components_mut();
-
-    let mut transforms_to_calc = entities_components
-        .query::<(Entity, &GlobalTransform)>()
+    let mut transforms_to_calc = transforms
+        .p0()
         .iter()
         .filter(|(e, _)| {
-            let Some(child_component) = entities_components.get_component::<ChildComponent>(*e) else {
+            let Some((_, Some(child_component), _)) = hierarchy.get(*e) else {
                 return true;
             };
 
-            !entities_components.contains_entity(child_component.parent)
+            entities.get(child_component.parent).is_none()
         })
         .map(|(e, _)| e)
         .collect::<VecDeque<_>>();
 
+    let mut global_transforms = transforms.p1();
+
     while let Some(transform) = transforms_to_calc.pop_front() {
-        let parent_global_transform = match entities_components.get_component::<ChildComponent>(transform) {
+        let Some((local_transform, child_component, children)) = hierarchy.get(transform) else {
+            continue;
+        };
+
+        let parent_global_transform = match child_component {
             None => GlobalTransform::IDENTITY,
-            Some(child_component) => match entities_components.get_component::<GlobalTransform>(child_component.parent) {
+            Some(child_component) => match global_transforms.get(child_component.parent) {
                 None => GlobalTransform::IDENTITY,
                 Some(&parent_global_transform) => parent_global_transform,
             }
@@ -165,17 +125,17 @@ pub fn calculate_global_transform(
 
         // todo: Check geometry operations
         // {
-        let Some(&local_transform) = entities_components.get_component::<LocalTransform>(transform) else {
+        let Some(&local_transform) = local_transform else {
             continue;
         };
-        let Some(global_transform) = entities_components.get_component_mut::<GlobalTransform>(transform) else {
+        let Some(global_transform) = global_transforms.get_mut(transform) else {
             continue;
         };
         global_transform.position = parent_global_transform.scale_rotation * local_transform.position + parent_global_transform.position;
         global_transform.scale_rotation = parent_global_transform.scale_rotation * (local_transform.rotation.to_matrix() * fruits_math::scale_matrix_3d(local_transform.scale));
         // }
 
-        let Some(children) = entities_components.get_component::<ParentComponent>(transform) else {
+        let Some(children) = children else {
             continue;
         };
 
@@ -183,4 +143,4 @@ pub fn calculate_global_transform(
             transforms_to_calc.push_back(child);
         }
     }
-}
\ No newline at end of file
+}
@@ -1,23 +1,27 @@
-use std::{collections::VecDeque, mem::MaybeUninit};
+use std::{collections::VecDeque, mem::MaybeUninit, sync::atomic::{AtomicUsize, Ordering}};
 
+/// Versions of the occupied places are odd and versions of the free places are even,
+/// so no index given out before matches a free place, even one restored from a clone.
+/// A place whose version cannot grow anymore is retired instead of being reused.
 pub struct VersionCollection<T> {
     items: Vec<DataWithVersion<T>>,
     free_places: VecDeque<usize>,
-    // reserved_places: VecDeque<usize>,
+    /// New places given out by `reserve` and not inserted yet. They follow the last place.
+    reserved_count: AtomicUsize,
     count: usize,
 }
 
 unsafe impl<S: Send> Send for VersionCollection<S> { }
 unsafe impl<S: Sync> Sync for VersionCollection<S> { }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub struct VersionIndex {
-    pub index: usize,
-    pub version: usize,
+    pub index: u32,
+    pub version: u32,
 }
 
 pub struct DataWithVersion<T> {
-    pub version: usize,
+    pub version: u32,
     pub data: MaybeUninit<T>,
 }
 
@@ -26,26 +30,31 @@ impl<T> VersionCollection<T> {
         Self {
             items: Vec::new(),
             free_places: VecDeque::new(),
+            reserved_count: AtomicUsize::new(0),
             count: 0,
         }
     }
 
     pub fn insert(&mut self, data: T) -> VersionIndex {
+        assert_eq!(*self.reserved_count.get_mut(), 0, "The reserved places should be flushed before inserting.");
+
         if let Some(index) = self.free_places.pop_front() {
-            let version = self.items[index].version;
+            let version = self.items[index].version + 1;
 
             self.items[index] = DataWithVersion::<T> {
                 data: MaybeUninit::new(data),
                 version,
             };
 
+            self.count += 1;
+
             return VersionIndex {
-                index,
+                index: index as u32,
                 version,
             }
         }
 
-        let index: usize = self.items.len();
+        let index = u32::try_from(self.items.len()).expect("VersionCollection cannot hold more than u32::MAX places.");
         // entities with version 0 cannot exist.
         let version = 1;
 
@@ -62,12 +71,48 @@ impl<T> VersionCollection<T> {
         }
     }
 
+    /// Gives out the index of a new place without inserting it, so it can be done concurrently.
+    /// The place is occupied by `flush_reserved`, which should be called before the next insert.
+    pub fn reserve_index(&self) -> VersionIndex {
+        let index = self.items.len() + self.reserved_count.fetch_add(1, Ordering::Relaxed);
+
+        VersionIndex {
+            index: u32::try_from(index).expect("VersionCollection cannot hold more than u32::MAX places."),
+            version: 1,
+        }
+    }
+
+    /// Occupies the reserved places in the order they were given out with the data created for their indices.
+    pub fn flush_reserved(&mut self, mut data: impl FnMut(VersionIndex) -> T) {
+        let reserved_count = std::mem::take(self.reserved_count.get_mut());
+
+        for _ in 0..reserved_count {
+            let index = VersionIndex {
+                index: self.items.len() as u32,
+                version: 1,
+            };
+
+            self.items.push(DataWithVersion::<T> {
+                data: MaybeUninit::new(data(index)),
+                version: index.version,
+            });
+
+            self.count += 1;
+        }
+    }
+
     pub fn remove(&mut self, index: VersionIndex) -> Option<T> {
         let Some(data_with_version) = self.get_data_with_version_mut(index) else {
             return None;
         };
 
-        data_with_version.version += 1;
+        // The last odd version cannot be followed by a new one, so the place stays free forever.
+        let is_retired = data_with_version.version == u32::MAX;
+
+        data_with_version.version = match is_retired {
+            true => u32::MAX - 1,
+            false => data_with_version.version + 1,
+        };
 
         let data = unsafe {
             let data: T = std::mem::transmute_copy(&data_with_version.data);
@@ -75,7 +120,9 @@ impl<T> VersionCollection<T> {
             data
         };
 
-        self.free_places.push_back(index.index);
+        if !is_retired {
+            self.free_places.push_back(index.index as usize);
+        }
 
         self.count -= 1;
 
@@ -91,9 +138,7 @@ impl<T> VersionCollection<T> {
     }
 
     fn get_data_with_version(&self, index: VersionIndex) -> Option<&DataWithVersion<T>> {
-        let Some(data_with_version) = self.items.get(index.index) else {
-            return None;
-        };
+        let data_with_version = self.items.get(index.index as usize)?;
 
         if index.version != data_with_version.version {
             return None;
@@ -103,7 +148,7 @@ impl<T> VersionCollection<T> {
     }
 
     fn get_data_with_version_mut(&mut self, index: VersionIndex) -> Option<&mut DataWithVersion<T>> {
-        let data_with_version = self.items.get_mut(index.index)?;
+        let data_with_version = self.items.get_mut(index.index as usize)?;
 
         if index.version != data_with_version.version {
             return None;
@@ -113,7 +158,7 @@ impl<T> VersionCollection<T> {
     }
 
     pub fn contains_index(&self, index: VersionIndex) -> bool {
-        let Some(data_with_version) = self.items.get(index.index) else {
+        let Some(data_with_version) = self.items.get(index.index as usize) else {
             return false;
         };
 
@@ -123,36 +168,84 @@ impl<T> VersionCollection<T> {
     pub fn len(&self) -> usize {
         self.count
     }
+
+    pub fn reserve(&mut self, additional: usize) {
+        self.items.reserve(additional.saturating_sub(self.free_places.len()));
+    }
+}
+
+/// Clones the items together with the versions and the free places order, so the clone gives out the same indices.
+impl<T: Clone> Clone for VersionCollection<T> {
+    fn clone(&self) -> Self {
+        let items = self.items.iter().map(|item| DataWithVersion {
+            version: item.version,
+            data: match is_occupied(item.version) {
+                true => MaybeUninit::new(unsafe { item.data.assume_init_ref() }.clone()),
+                false => MaybeUninit::uninit(),
+            },
+        }).collect();
+
+        Self {
+            items,
+            free_places: self.free_places.clone(),
+            reserved_count: AtomicUsize::new(self.reserved_count.load(Ordering::Relaxed)),
+            count: self.count,
+        }
+    }
 }
 
 impl<T> Drop for VersionCollection<T> {
     fn drop(&mut self) {
-        self.free_places.make_contiguous();
-        self.free_places.as_mut_slices().0.sort();
+        for item in self.items.iter_mut().filter(|i| is_occupied(i.version)) {
+            unsafe { item.data.assume_init_drop() };
+        }
+    }
+}
 
-        let mut free_places_iter = self.free_places.iter();
+fn is_occupied(version: u32) -> bool {
+    version % 2 == 1
+}
 
-        let mut free_place = free_places_iter.next();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        for (index, item) in self.items.iter_mut().enumerate() {
-            let should_drop = loop {
-                let Some(&free_index) = free_place else {
-                    break true;
-                };
+    #[test]
+    fn place_with_last_version_is_retired() {
+        let mut collection = VersionCollection::new();
 
-                if free_index == index {
-                    break false;
-                }
+        let first = collection.insert(String::from("first"));
+        collection.items[first.index as usize].version = u32::MAX;
 
-                if free_index < index {
-                    free_place = free_places_iter.next();
-                }
-            };
+        let first = VersionIndex { version: u32::MAX, ..first };
 
-            if should_drop {
-                drop(unsafe { std::mem::transmute_copy::<_, T>(item.data.assume_init_ref()) });
-                continue;
-            }
-        }
+        assert_eq!(collection.remove(first).as_deref(), Some("first"));
+        assert!(!collection.contains_index(first));
+
+        let second = collection.insert(String::from("second"));
+
+        assert_eq!(second, VersionIndex { index: 1, version: 1 });
+        assert_eq!(collection.len(), 1);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn reserved_places_follow_the_last_place() {
+        let mut collection = VersionCollection::new();
+
+        let first = collection.insert(0);
+        collection.remove(first);
+
+        let reserved = [collection.reserve_index(), collection.reserve_index()];
+
+        assert_eq!(reserved, [VersionIndex { index: 1, version: 1 }, VersionIndex { index: 2, version: 1 }]);
+        assert!(!collection.contains_index(reserved[0]));
+
+        collection.flush_reserved(|index| index.index * 10);
+
+        assert_eq!(collection.get(reserved[0]), Some(&10));
+        assert_eq!(collection.get(reserved[1]), Some(&20));
+        assert_eq!(collection.len(), 2);
+
+        assert_eq!(collection.insert(30), VersionIndex { index: 0, version: 3 });
+    }
+}
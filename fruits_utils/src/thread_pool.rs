@@ -1,11 +1,16 @@
 use std::{
+    any::Any,
+    mem,
+    panic::{self, AssertUnwindSafe},
     sync::{
+        atomic::{AtomicUsize, Ordering},
         mpsc::{
             Sender,
             self,
             Receiver
         },
         Arc,
+        Condvar,
         Mutex
     },
     thread,
@@ -59,6 +64,10 @@ impl<J: Job> ThreadPool<J> {
         self.message_sender.send(Message::JobRequest(job)).unwrap();
     }
 
+    pub fn threads_count(&self) -> usize {
+        self.threads.len()
+    }
+
     fn run_worker(_id: usize, message_receiver: Arc<Mutex<Receiver<Message<J>>>>) -> thread::JoinHandle<()> {
         thread::spawn(move || {
             loop {
@@ -75,6 +84,87 @@ impl<J: Job> ThreadPool<J> {
     }
 }
 
+impl ThreadPool {
+    /// Executes every batch index on the pool and the calling thread, returning once all of them have finished.
+    /// The calling thread takes batches too, so it is fine to call it from a pool job.
+    pub fn execute_batches(&self, batches_count: usize, execute_batch: impl Fn(usize) + Sync) {
+        if batches_count == 0 {
+            return;
+        }
+
+        let execute_batch: &(dyn Fn(usize) + Sync) = &execute_batch;
+
+        let batches = Arc::new(Batches {
+            count: batches_count,
+            next: AtomicUsize::new(0),
+            completed: Mutex::new(0),
+            all_completed: Condvar::new(),
+            panic: Mutex::new(None),
+            // Batches are only claimed before all of them complete, and this function waits for that.
+            execute_batch: unsafe { mem::transmute::<*const (dyn Fn(usize) + Sync + '_), *const (dyn Fn(usize) + Sync)>(execute_batch) },
+        });
+
+        for _ in 0..(batches_count - 1).min(self.threads_count()) {
+            let batches = Arc::clone(&batches);
+            self.push_job(Box::new(move || batches.execute_remaining()));
+        }
+
+        batches.execute_remaining();
+
+        let mut completed = batches.completed.lock().unwrap();
+
+        while *completed < batches_count {
+            completed = batches.all_completed.wait(completed).unwrap();
+        }
+
+        drop(completed);
+
+        let panic = batches.panic.lock().unwrap().take();
+
+        if let Some(payload) = panic {
+            panic::resume_unwind(payload);
+        }
+    }
+}
+
+struct Batches {
+    count: usize,
+    next: AtomicUsize,
+    completed: Mutex<usize>,
+    all_completed: Condvar,
+    panic: Mutex<Option<Box<dyn Any + Send>>>,
+    execute_batch: *const (dyn Fn(usize) + Sync),
+}
+
+unsafe impl Send for Batches { }
+unsafe impl Sync for Batches { }
+
+impl Batches {
+    fn execute_remaining(&self) {
+        loop {
+            let index = self.next.fetch_add(1, Ordering::Relaxed);
+
+            if index >= self.count {
+                return;
+            }
+
+            let execute_batch = unsafe { &*self.execute_batch };
+
+            if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(|| execute_batch(index))) {
+                self.panic.lock().unwrap().get_or_insert(payload);
+            }
+
+            let mut completed = self.completed.lock().unwrap();
+
+            *completed += 1;
+
+            if *completed == self.count {
+                self.all_completed.notify_all();
+            }
+        }
+    }
+}
+
 impl<J: Job> Drop for ThreadPool<J> {
     fn drop(&mut self) {
         for _ in 0..self.threads.len() {